@@ -0,0 +1,54 @@
+#![crate_id="cargo-clean"]
+#![feature(phase)]
+
+extern crate cargo;
+extern crate hammer;
+extern crate serialize;
+
+#[phase(plugin, link)]
+extern crate log;
+
+use std::os;
+use hammer::{FlagConfig,FlagConfiguration};
+use cargo::{execute_main_without_stdin,CLIResult,CLIError,ToResult};
+use cargo::ops;
+use cargo::util::important_paths::find_project;
+use cargo::util::ToCLI;
+
+#[deriving(PartialEq,Clone,Decodable,Encodable)]
+pub struct Options {
+    manifest_path: Option<String>,
+    package: Option<String>,
+    release: bool,
+    target: Option<String>
+}
+
+impl FlagConfig for Options {
+    fn config(_: Option<Options>, config: FlagConfiguration) -> FlagConfiguration {
+        config.short("package", 'p')
+    }
+}
+
+fn main() {
+    execute_main_without_stdin(execute, "Remove the files compiling the current project created");
+}
+
+fn execute(options: Options) -> CLIResult<Option<()>> {
+    debug!("executing; cmd=cargo-clean; args={}", os::args());
+
+    let root = match options.manifest_path {
+        Some(ref path) => Path::new(path.as_slice()),
+        None => try!(find_project(os::getcwd(), "Cargo.toml")
+                    .map(|path| path.join("Cargo.toml"))
+                    .to_result(|err|
+                        CLIError::new("Could not find Cargo.toml in this directory or any parent directory", Some(err), 102)))
+    };
+
+    let opts = ops::CleanOptions {
+        package: options.package.as_ref().map(|package| package.as_slice()),
+        release: options.release,
+        target: options.target.as_ref().map(|target| target.as_slice())
+    };
+
+    ops::clean(&root, &opts).map(|_| None).to_cli(101)
+}
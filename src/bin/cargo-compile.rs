@@ -9,7 +9,7 @@ extern crate serialize;
 extern crate log;
 
 use std::os;
-use hammer::FlagConfig;
+use hammer::{FlagConfig,FlagConfiguration};
 use cargo::{execute_main_without_stdin,CLIResult,CLIError,ToResult};
 use cargo::ops;
 use cargo::util::important_paths::find_project;
@@ -17,13 +17,23 @@ use cargo::util::{ToCLI,simple_human};
 
 #[deriving(PartialEq,Clone,Decodable,Encodable)]
 pub struct Options {
-    manifest_path: Option<String>
+    manifest_path: Option<String>,
+    jobs: Option<uint>,
+    release: bool,
+    target: Option<String>,
+    features: Option<String>,
+    no_default_features: bool,
+    all: bool
 }
 
-impl FlagConfig for Options {}
+impl FlagConfig for Options {
+    fn config(_: Option<Options>, config: FlagConfiguration) -> FlagConfiguration {
+        config.short("jobs", 'j')
+    }
+}
 
 fn main() {
-    execute_main_without_stdin(execute);
+    execute_main_without_stdin(execute, "Compile the current project and its dependencies");
 }
 
 fn execute(options: Options) -> CLIResult<Option<()>> {
@@ -37,5 +47,11 @@ fn execute(options: Options) -> CLIResult<Option<()>> {
                         CLIError::new("Could not find Cargo.toml in this directory or any parent directory", Some(err), 102)))
     };
 
-    ops::compile(&root).map(|_| None).to_cli(101)
+    let features = options.features.as_ref().map(|features| features.as_slice());
+    let mut compile_options = try!(ops::CompileOptions::from_flags(options.jobs, options.release, features, options.no_default_features).to_cli(1));
+
+    compile_options.target = options.target;
+    compile_options.all = options.all;
+
+    ops::compile(&root, &compile_options).map(|_| None).to_cli(101)
 }
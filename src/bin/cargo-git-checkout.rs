@@ -8,7 +8,7 @@ extern crate url;
 use hammer::FlagConfig;
 use cargo::{execute_main_without_stdin,CLIResult,CLIError,ToResult};
 use cargo::core::source::Source;
-use cargo::sources::git::{GitSource,GitRemote};
+use cargo::sources::git::{GitSource,GitRemote,GitReference};
 use url::Url;
 
 #[deriving(PartialEq,Clone,Decodable)]
@@ -23,7 +23,7 @@ struct Options {
 impl FlagConfig for Options {}
 
 fn main() {
-    execute_main_without_stdin(execute);
+    execute_main_without_stdin(execute, "Check out a git repository at the given reference");
 }
 
 fn execute(options: Options) -> CLIResult<Option<()>> {
@@ -33,7 +33,7 @@ fn execute(options: Options) -> CLIResult<Option<()>> {
         CLIError::new(format!("The URL `{}` you passed was not a valid URL", url), None::<&str>, 1)));
 
     let remote = GitRemote::new(url, verbose);
-    let source = GitSource::new(remote, reference, Path::new(database_path), Path::new(checkout_path));
+    let source = GitSource::new(remote, GitReference::for_str(reference), Path::new(database_path), Path::new(checkout_path));
     try!(source.update().map_err(|e| {
         CLIError::new(format!("Couldn't update {}: {}", source, e), None::<&str>, 1)
     }));
@@ -0,0 +1,43 @@
+#![crate_id="cargo-new"]
+#![feature(phase)]
+
+extern crate cargo;
+extern crate hammer;
+extern crate serialize;
+
+#[phase(plugin, link)]
+extern crate log;
+
+use std::os;
+use hammer::FlagConfig;
+use cargo::{execute_main_without_stdin,CLIResult,CLIError,ToResult};
+use cargo::ops;
+use cargo::util::ToCLI;
+
+#[deriving(PartialEq,Clone,Decodable,Encodable)]
+pub struct Options {
+    bin: bool,
+    git: bool,
+    rest: Vec<String>
+}
+
+impl FlagConfig for Options {}
+
+fn main() {
+    execute_main_without_stdin(execute, "Create a new project in a new directory");
+}
+
+fn execute(options: Options) -> CLIResult<Option<()>> {
+    debug!("executing; cmd=cargo-new; args={}", os::args());
+
+    let path = try!(options.rest.as_slice().head().to_result(|_|
+        CLIError::new("A path for the new project is required, e.g. `cargo new foo`", None::<&str>, 1)));
+
+    let opts = ops::NewOptions {
+        path: path.as_slice(),
+        bin: options.bin,
+        git: options.git
+    };
+
+    ops::new(opts).map(|_| None).to_cli(101)
+}
@@ -0,0 +1,42 @@
+#![crate_id="cargo-publish"]
+#![feature(phase)]
+
+extern crate cargo;
+extern crate hammer;
+extern crate serialize;
+
+#[phase(plugin, link)]
+extern crate log;
+
+use std::os;
+use hammer::FlagConfig;
+use cargo::{execute_main_without_stdin,CLIResult,CLIError,ToResult};
+use cargo::ops;
+use cargo::util::important_paths::find_project;
+use cargo::util::ToCLI;
+
+#[deriving(PartialEq,Clone,Decodable,Encodable)]
+pub struct Options {
+    manifest_path: Option<String>,
+    no_verify: bool
+}
+
+impl FlagConfig for Options {}
+
+fn main() {
+    execute_main_without_stdin(execute, "Upload the current project to the registry");
+}
+
+fn execute(options: Options) -> CLIResult<Option<()>> {
+    debug!("executing; cmd=cargo-publish; args={}", os::args());
+
+    let root = match options.manifest_path {
+        Some(path) => Path::new(path),
+        None => try!(find_project(os::getcwd(), "Cargo.toml")
+                    .map(|path| path.join("Cargo.toml"))
+                    .to_result(|err|
+                        CLIError::new("Could not find Cargo.toml in this directory or any parent directory", Some(err), 102)))
+    };
+
+    ops::publish(&root, !options.no_verify).map(|_| None).to_cli(101)
+}
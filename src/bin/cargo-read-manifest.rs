@@ -17,7 +17,7 @@ struct Options {
 impl FlagConfig for Options {}
 
 fn main() {
-    execute_main_without_stdin(execute);
+    execute_main_without_stdin(execute, "Print a package's manifest as JSON");
 }
 
 fn execute(options: Options) -> CLIResult<Option<Package>> {
@@ -0,0 +1,69 @@
+#![crate_id="cargo-run"]
+#![feature(phase)]
+
+extern crate cargo;
+extern crate hammer;
+extern crate serialize;
+
+#[phase(plugin, link)]
+extern crate log;
+
+use std::os;
+use std::io::process::{ExitStatus,ExitSignal};
+use hammer::{FlagConfig,FlagConfiguration};
+use cargo::{execute_main_without_stdin,CLIResult,CLIError,ToResult};
+use cargo::ops;
+use cargo::util::important_paths::find_project;
+use cargo::util::ToCLI;
+
+#[deriving(PartialEq,Clone,Decodable,Encodable)]
+pub struct Options {
+    manifest_path: Option<String>,
+    bin: Option<String>,
+    jobs: Option<uint>,
+    release: bool,
+    features: Option<String>,
+    no_default_features: bool,
+    rest: Vec<String>
+}
+
+impl FlagConfig for Options {
+    fn config(_: Option<Options>, config: FlagConfiguration) -> FlagConfiguration {
+        config.short("jobs", 'j')
+    }
+}
+
+fn main() {
+    execute_main_without_stdin(execute, "Compile the current project and run one of its programs");
+}
+
+fn execute(options: Options) -> CLIResult<Option<()>> {
+    debug!("executing; cmd=cargo-run; args={}", os::args());
+
+    let root = match options.manifest_path {
+        Some(ref path) => Path::new(path.as_slice()),
+        None => try!(find_project(os::getcwd(), "Cargo.toml")
+                    .map(|path| path.join("Cargo.toml"))
+                    .to_result(|err|
+                        CLIError::new("Could not find Cargo.toml in this directory or any parent directory", Some(err), 102)))
+    };
+
+    let features = options.features.as_ref().map(|features| features.as_slice());
+    let compile_options = try!(ops::CompileOptions::from_flags(options.jobs, options.release, features, options.no_default_features).to_cli(1));
+
+    // Everything after `--` is passed to the program
+    let args = match options.rest.as_slice().head() {
+        Some(first) if first.as_slice() == "--" => options.rest.tail(),
+        _ => options.rest.as_slice()
+    };
+
+    let bin = options.bin.as_ref().map(|bin| bin.as_slice());
+
+    match try!(ops::run(&root, &compile_options, bin, args).to_cli(101)) {
+        None => (),
+        Some(ExitStatus(code)) => os::set_exit_status(code),
+        Some(ExitSignal(_)) => os::set_exit_status(101)
+    }
+
+    Ok(None)
+}
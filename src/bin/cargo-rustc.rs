@@ -1,8 +1,106 @@
 #![crate_id="cargo-rustc"]
+#![feature(phase)]
 
 extern crate cargo;
+extern crate hammer;
+extern crate serialize;
+
+#[phase(plugin, link)]
+extern crate log;
+
+use std::os;
+use std::collections::HashMap;
+use hammer::{FlagConfig,FlagConfiguration};
+use cargo::{execute_main,CLIResult};
+use cargo::core::{Package,PackageSet};
+use cargo::core::resolver::Resolve;
+use cargo::ops;
+use cargo::ops::Artifact;
+use cargo::sources::PathSource;
+use cargo::util::{ToCLI,CargoResult,simple_human};
+
+#[deriving(PartialEq,Clone,Decodable)]
+pub struct Options {
+    jobs: Option<uint>,
+    release: bool,
+    target: Option<String>
+}
+
+impl FlagConfig for Options {
+    fn config(_: Option<Options>, config: FlagConfiguration) -> FlagConfiguration {
+        config.short("jobs", 'j')
+    }
+}
+
+/**
+  A package as `cargo-read-manifest` and the other commands print it. Only
+  the fields needed to find it again are read; the rest of the package is
+  loaded from its manifest.
+*/
+#[deriving(Decodable)]
+struct PackageInput {
+    name: String,
+    version: String,
+    manifest_path: String
+}
+
+/**
+  The input on stdin: the package to compile, the packages it resolved to,
+  and optionally the features enabled on each package by name.
+*/
+#[deriving(Decodable)]
+struct Input {
+    package: PackageInput,
+    dependencies: Vec<PackageInput>,
+    features: Option<HashMap<String, Vec<String>>>
+}
+
+#[deriving(Encodable)]
+struct Output {
+    artifacts: Vec<Artifact>
+}
 
 fn main() {
-    // Standalone cargo-rustc will go here
-    unimplemented!();
+    execute_main(execute, "Compile a package and its already resolved dependencies, read as JSON from stdin");
+}
+
+fn execute(options: Options, input: Input) -> CLIResult<Option<Output>> {
+    debug!("executing; cmd=cargo-rustc; args={}", os::args());
+
+    let mut compile_options = try!(ops::CompileOptions::from_flags(options.jobs, options.release, None, false).to_cli(1));
+    compile_options.quiet = true;
+
+    compile_options.target = options.target;
+
+    let Input { package, dependencies, features } = input;
+
+    let package = try!(read_package(&package).to_cli(1));
+    let mut deps = Vec::new();
+
+    for dep in dependencies.iter() {
+        deps.push(try!(read_package(dep).to_cli(1)));
+    }
+
+    let ids = deps.iter().map(|dep| dep.get_package_id().clone()).collect();
+    let resolve = Resolve::new(ids, features.unwrap_or_else(|| HashMap::new()));
+
+    let artifacts = try!(ops::compile_packages(&package, &PackageSet::new(deps.as_slice()), &resolve, &compile_options).to_cli(101));
+
+    Ok(Some(Output { artifacts: artifacts }))
+}
+
+/**
+  Loads a package from the manifest it names, which must still describe the
+  same name and version.
+*/
+fn read_package(input: &PackageInput) -> CargoResult<Package> {
+    let package = try!(PathSource::read_package(&Path::new(input.manifest_path.as_slice())));
+    let version = package.get_version().to_str();
+
+    if package.get_name() != input.name.as_slice() || version != input.version {
+        return Err(simple_human(format!("The manifest at `{}` is for {} {}, not {} {}",
+                                        input.manifest_path, package.get_name(), version, input.name, input.version)));
+    }
+
+    Ok(package)
 }
@@ -0,0 +1,50 @@
+#![crate_id="cargo-test"]
+#![feature(phase)]
+
+extern crate cargo;
+extern crate hammer;
+extern crate serialize;
+
+#[phase(plugin, link)]
+extern crate log;
+
+use std::os;
+use hammer::FlagConfig;
+use cargo::{execute_main_without_stdin,CLIResult,CLIError,ToResult};
+use cargo::ops;
+use cargo::util::important_paths::find_project;
+use cargo::util::ToCLI;
+
+#[deriving(PartialEq,Clone,Decodable,Encodable)]
+pub struct Options {
+    manifest_path: Option<String>,
+    rest: Vec<String>
+}
+
+impl FlagConfig for Options {}
+
+fn main() {
+    execute_main_without_stdin(execute, "Compile and run the current project's tests");
+}
+
+fn execute(options: Options) -> CLIResult<Option<()>> {
+    debug!("executing; cmd=cargo-test; args={}", os::args());
+
+    let root = match options.manifest_path {
+        Some(path) => Path::new(path),
+        None => try!(find_project(os::getcwd(), "Cargo.toml")
+                    .map(|path| path.join("Cargo.toml"))
+                    .to_result(|err|
+                        CLIError::new("Could not find Cargo.toml in this directory or any parent directory", Some(err), 102)))
+    };
+
+    // Everything after the flags is a filter on the names of the tests to
+    // run, and the harnesses only take one
+    if options.rest.len() > 1 {
+        return Err(CLIError::new(format!("Only one test name filter can be given, but got {}", options.rest.connect(" ")), None::<&str>, 1));
+    }
+
+    let filter = options.rest.as_slice().head().map(|f| f.as_slice());
+
+    ops::run_tests(&root, filter).map(|_| None).to_cli(101)
+}
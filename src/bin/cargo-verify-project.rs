@@ -1,52 +1,51 @@
 #![crate_id="cargo-verify-project"]
 
+extern crate cargo;
 extern crate toml = "github.com/mneumann/rust-toml#toml";
-extern crate getopts;
+extern crate hammer;
+extern crate serialize;
 
-use std::os::{args,set_exit_status};
-use getopts::{reqopt,getopts};
+use std::os::set_exit_status;
+use hammer::{FlagConfig,FlagConfiguration};
+use cargo::{execute_main_without_stdin,CLIResult};
 
 /**
     cargo-verify-project --manifest=LOCATION
 */
 
-fn main() {
-    let arguments = args();
-
-    let opts = vec!(
-        reqopt("m", "manifest", "the location of the manifest", "MANIFEST")
-    );
-
-    let matches = match getopts(arguments.tail(), opts.as_slice()) {
-        Ok(m) => m,
-        Err(_) => {
-            fail("missing-argument", "manifest");
-            return;
-        }
-    };
+#[deriving(PartialEq,Clone,Decodable)]
+struct Options {
+    manifest: String
+}
 
-    if !matches.opt_present("m") {
-        fail("missing-argument", "manifest");
-        return;
+impl FlagConfig for Options {
+    fn config(_: Option<Options>, config: FlagConfiguration) -> FlagConfiguration {
+        config.short("manifest", 'm')
     }
+}
+
+fn main() {
+    execute_main_without_stdin(execute, "Check that a manifest exists and is valid TOML");
+}
 
-    let manifest = matches.opt_str("m").unwrap();
-    let file = Path::new(manifest);
+fn execute(options: Options) -> CLIResult<Option<()>> {
+    let file = Path::new(options.manifest);
 
     if !file.exists() {
         fail("invalid", "not-found");
-        return;
+        return Ok(None);
     }
 
     match toml::parse_from_file(file.as_str().unwrap()) {
         Err(_) => {
             fail("invalid", "invalid-format");
-            return;
+            return Ok(None);
         },
         Ok(r) => r
     };
 
     println!("{}", "{ \"success\": \"true\" }");
+    Ok(None)
 }
 
 fn fail(reason: &str, value: &str) {
@@ -8,12 +8,17 @@ extern crate serialize;
 extern crate log;
 
 use hammer::{FlagConfig,FlagConfiguration};
+use std::io::fs;
+use std::io::process::{ExitStatus,ExitSignal};
 use std::os;
 use serialize::Encodable;
 use cargo::{NoFlags,execute_main_without_stdin,handle_error};
 use cargo::core::errors::{CLIError,CLIResult,ToResult};
-use cargo::util::important_paths::find_project;
+use cargo::util;
+use cargo::util::{CargoError,lev_distance};
+use cargo::util::important_paths::{find_project,find_workspace_root};
 use cargo::util::config;
+use cargo::util::result::ProcessError;
 
 fn main() {
     execute();
@@ -21,7 +26,9 @@ fn main() {
 
 #[deriving(Encodable)]
 struct ProjectLocation {
-    root: String
+    root: String,
+    // The root of the workspace the project is a member of
+    workspace_root: Option<String>
 }
 
 /**
@@ -32,28 +39,175 @@ struct ProjectLocation {
 fn execute() {
     debug!("executing; cmd=cargo; args={}", os::args());
 
-    let (cmd, _) = match process(os::args()) {
+    let (cmd, args) = match process(os::args()) {
         Ok((cmd, args)) => (cmd, args),
         Err(err) => return handle_error(err)
     };
 
     if cmd == "config-for-key".to_str() {
         log!(4, "cmd == config-for-key");
-        execute_main_without_stdin(config_for_key)
+        execute_main_without_stdin(config_for_key, "Print the configured value of a key")
     }
     else if cmd == "config-list".to_str() {
         log!(4, "cmd == config-list");
-        execute_main_without_stdin(config_list)
+        execute_main_without_stdin(config_list, "Print every configured key and its value")
     }
     else if cmd == "locate-project".to_str() {
         log!(4, "cmd == locate-project");
-        execute_main_without_stdin(locate_project)
+        execute_main_without_stdin(locate_project, "Print the root of the project containing the current directory")
+    }
+    else if cmd == "help".to_str() || cmd == "--help".to_str() {
+        log!(4, "cmd == help");
+        help(args.as_slice())
+    }
+    else if cmd == "--list".to_str() {
+        log!(4, "cmd == --list");
+        list_commands()
     }
     else {
-        // TODO: Handle automatic dispatching to cargo-*
-        debug!("unknown command");
-        println!("Automatic execing of cargo-* commands has not yet been implemented. Call cargo-* commands directly for now");
+        dispatch(cmd.as_slice(), args.as_slice())
+    }
+}
+
+static BUILTIN_COMMANDS: &'static [&'static str] = &["config-for-key", "config-list", "help", "locate-project"];
+
+static USAGE: &'static str = "Usage: cargo <command> [<args>...]
+       cargo --list
+
+See `cargo help <command>` for more information on a specific command.
+";
+
+/**
+  `cargo help foo` is `cargo foo --help`, which works the same way for the
+  builtins and for `cargo-*` executables.
+*/
+fn help(args: &[String]) {
+    let cmd = match args.head() {
+        Some(cmd) => cmd,
+        None => return print!("{}", USAGE)
+    };
+
+    match os::self_exe_name() {
+        Some(cargo) => exec(&cargo, [cmd.clone(), "--help".to_str()]),
+        None => handle_error(CLIError::new("Could not find the cargo executable", None::<&str>, 101))
+    }
+}
+
+/**
+  Any other command `foo` is handled by an executable named `cargo-foo`,
+  which is passed the remaining arguments and shares cargo's stdio. Its
+  exit status becomes cargo's.
+*/
+fn dispatch(cmd: &str, args: &[String]) {
+    let path = match find_command(cmd) {
+        Some(path) => path,
+        None => return handle_error(unknown_command(cmd))
+    };
+
+    debug!("dispatching; cmd={}; path={}", cmd, path.display());
+    exec(&path, args)
+}
+
+fn exec(path: &Path, args: &[String]) {
+    match util::process(path.display().to_str().as_slice()).args(args).exec() {
+        Ok(()) => (),
+        Err(CargoError { kind: ProcessError(ExitStatus(code), _), .. }) => os::set_exit_status(code),
+        Err(CargoError { kind: ProcessError(ExitSignal(_), _), .. }) => os::set_exit_status(101),
+        Err(err) => handle_error(CLIError::new(format!("Could not execute `{}`", path.display()), Some(err), 101))
+    }
+}
+
+/**
+  The directories searched for `cargo-*` executables, in order: the one
+  containing `cargo` itself, those on `$PATH`, and those listed under
+  `subcommand-paths` in .cargo/config.
+*/
+fn search_dirs() -> Vec<Path> {
+    let mut dirs = Vec::new();
+
+    match os::self_exe_path() {
+        Some(dir) => dirs.push(dir),
+        None => ()
     }
+
+    match os::getenv("PATH") {
+        Some(path) => {
+            for dir in path.as_slice().split(':').filter(|dir| !dir.is_empty()) {
+                dirs.push(Path::new(dir));
+            }
+        },
+        None => ()
+    }
+
+    match config::get_config(os::getcwd(), "subcommand-paths") {
+        Ok(value) => match *value.get_value() {
+            config::String(ref dir) => dirs.push(Path::new(dir.as_slice())),
+            config::List(ref list) => {
+                for dir in list.iter() {
+                    dirs.push(Path::new(dir.as_slice()));
+                }
+            }
+        },
+        Err(_) => ()
+    }
+
+    dirs
+}
+
+fn find_command(cmd: &str) -> Option<Path> {
+    let name = format!("cargo-{}", cmd);
+
+    search_dirs().move_iter()
+        .map(|dir| dir.join(name.as_slice()))
+        .find(|path| path.is_file())
+}
+
+fn all_commands() -> Vec<String> {
+    let mut commands: Vec<String> = BUILTIN_COMMANDS.iter().map(|cmd| cmd.to_str()).collect();
+
+    for dir in search_dirs().iter() {
+        let entries = match fs::readdir(dir) {
+            Ok(entries) => entries,
+            Err(_) => continue
+        };
+
+        for entry in entries.iter() {
+            match entry.filename_str() {
+                Some(name) if name.starts_with("cargo-") && entry.is_file() => {
+                    commands.push(name.slice_from("cargo-".len()).to_str());
+                },
+                _ => ()
+            }
+        }
+    }
+
+    commands.sort();
+    commands.dedup();
+    commands
+}
+
+fn list_commands() {
+    println!("Installed commands:");
+
+    for cmd in all_commands().iter() {
+        println!("    {}", cmd);
+    }
+}
+
+fn unknown_command(cmd: &str) -> CLIError {
+    let mut msg = format!("No such subcommand `{}`", cmd);
+
+    let closest = all_commands().move_iter()
+        .map(|candidate| (lev_distance(cmd, candidate.as_slice()), candidate))
+        .filter(|&(distance, _)| distance <= 3)
+        .min_by(|&(distance, _)| distance);
+
+    match closest {
+        Some((_, candidate)) => msg.push_str(format!("\n\nDid you mean `{}`?", candidate).as_slice()),
+        None => ()
+    }
+
+    CLIError::new(msg, None::<&str>, 1)
 }
 
 fn process(args: Vec<String>) -> CLIResult<(String, Vec<String>)> {
@@ -127,5 +281,11 @@ fn locate_project(_: NoFlags) -> CLIResult<Option<ProjectLocation>> {
     let string = try!(root.as_str().to_result(|_|
         CLIError::new(format!("Your project path contains characters not representable in Unicode: {}", os::getcwd().display()), None::<&str>, 1)));
 
-    Ok(Some(ProjectLocation { root: string.to_str() }))
+    let workspace_root = try!(find_workspace_root(&root).to_result(|err|
+        CLIError::new(err.to_str(), None::<&str>, 1)));
+
+    Ok(Some(ProjectLocation {
+        root: string.to_str(),
+        workspace_root: workspace_root.map(|root| root.display().to_str())
+    }))
 }
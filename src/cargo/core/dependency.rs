@@ -5,31 +5,79 @@ use util::CargoResult;
 #[deriving(PartialEq,Clone,Show)]
 pub struct Dependency {
     name: String,
-    req: VersionReq
+    req: VersionReq,
+    kind: DependencyKind,
+    // Optional dependencies are only used when a feature enables them
+    optional: bool,
+    // The features of the dependency this dependency asks for
+    features: Vec<String>,
+    default_features: bool
+}
+
+/**
+ * Development dependencies are only needed to build a package's tests. They
+ * are never required of packages that depend on it.
+ */
+#[deriving(PartialEq,Clone,Show)]
+pub enum DependencyKind {
+    Normal,
+    Development
 }
 
 impl Dependency {
     pub fn new(name: &str, req: &VersionReq) -> Dependency {
         Dependency {
             name: name.to_str(),
-            req: req.clone()
+            req: req.clone(),
+            kind: Normal,
+            optional: false,
+            features: Vec::new(),
+            default_features: true
         }
     }
 
     pub fn parse(name: &str, version: &str) -> CargoResult<Dependency> {
         Ok(Dependency {
             name: name.to_str(),
-            req: try!(VersionReq::parse(version))
+            req: try!(VersionReq::parse(version)),
+            kind: Normal,
+            optional: false,
+            features: Vec::new(),
+            default_features: true
         })
     }
 
     pub fn exact(name: &str, version: &Version) -> Dependency {
         Dependency {
             name: name.to_str(),
-            req: VersionReq::exact(version)
+            req: VersionReq::exact(version),
+            kind: Normal,
+            optional: false,
+            features: Vec::new(),
+            default_features: true
         }
     }
 
+    pub fn kind(mut self, kind: DependencyKind) -> Dependency {
+        self.kind = kind;
+        self
+    }
+
+    pub fn optional(mut self, optional: bool) -> Dependency {
+        self.optional = optional;
+        self
+    }
+
+    pub fn features(mut self, features: Vec<String>) -> Dependency {
+        self.features = features;
+        self
+    }
+
+    pub fn default_features(mut self, default_features: bool) -> Dependency {
+        self.default_features = default_features;
+        self
+    }
+
     pub fn get_version_req<'a>(&'a self) -> &'a VersionReq {
         &self.req
     }
@@ -37,19 +85,61 @@ impl Dependency {
     pub fn get_name<'a>(&'a self) -> &'a str {
         self.name.as_slice()
     }
+
+    pub fn get_kind(&self) -> DependencyKind {
+        self.kind
+    }
+
+    /**
+     * Whether packages depending on this dependency's parent need it too
+     */
+    pub fn is_transitive(&self) -> bool {
+        self.kind == Normal
+    }
+
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+
+    pub fn get_features<'a>(&'a self) -> &'a [String] {
+        self.features.as_slice()
+    }
+
+    pub fn uses_default_features(&self) -> bool {
+        self.default_features
+    }
+
+    /**
+     * Whether the dependency is used when the parent has `features` enabled.
+     * An optional dependency is enabled by the feature named after it.
+     */
+    pub fn is_active(&self, features: &[String]) -> bool {
+        !self.optional || features.iter().any(|feature| feature.as_slice() == self.name.as_slice())
+    }
 }
 
 #[deriving(PartialEq,Clone,Encodable)]
 pub struct SerializedDependency {
     name: String,
-    req: String
+    req: String,
+    kind: &'static str,
+    optional: bool,
+    features: Vec<String>,
+    default_features: bool
 }
 
 impl SerializedDependency {
     pub fn from_dependency(dep: &Dependency) -> SerializedDependency {
         SerializedDependency {
             name: dep.get_name().to_str(),
-            req: dep.get_version_req().to_str()
+            req: dep.get_version_req().to_str(),
+            kind: match dep.get_kind() {
+                Normal => "normal",
+                Development => "dev"
+            },
+            optional: dep.is_optional(),
+            features: Vec::from_slice(dep.get_features()),
+            default_features: dep.uses_default_features()
         }
     }
 }
@@ -1,6 +1,7 @@
 use std::result;
 use std::fmt;
 use std::fmt::{Show,Formatter};
+use std::collections::HashMap;
 use semver::Version;
 use serialize::{Encoder,Encodable};
 use core::source::SourceId;
@@ -18,7 +19,12 @@ pub struct Manifest {
     authors: Vec<String>,
     targets: Vec<Target>,
     target_dir: Path,
-    sources: Vec<SourceId>
+    sources: Vec<SourceId>,
+    // The sources of development dependencies, only needed for tests
+    dev_sources: Vec<SourceId>,
+    profiles: Vec<Profile>,
+    // The script or command to run before compiling the package
+    build: Option<String>
 }
 
 impl Show for Manifest {
@@ -32,6 +38,7 @@ pub struct SerializedManifest {
     name: String,
     version: String,
     dependencies: Vec<SerializedDependency>,
+    features: HashMap<String, Vec<String>>,
     authors: Vec<String>,
     targets: Vec<Target>,
     target_dir: String
@@ -39,14 +46,7 @@ pub struct SerializedManifest {
 
 impl<E, S: Encoder<E>> Encodable<S, E> for Manifest {
     fn encode(&self, s: &mut S) -> Result<(), E> {
-        SerializedManifest {
-            name: self.summary.get_name().to_str(),
-            version: self.summary.get_version().to_str(),
-            dependencies: self.summary.get_dependencies().iter().map(|d| SerializedDependency::from_dependency(d)).collect(),
-            authors: self.authors.clone(),
-            targets: self.targets.clone(),
-            target_dir: self.target_dir.display().to_str()
-        }.encode(s)
+        self.serialize().encode(s)
     }
 }
 
@@ -83,6 +83,92 @@ impl LibKind {
     }
 }
 
+/**
+ * A named set of options to compile with. Every manifest has the `dev`,
+ * `release` and `test` profiles, whose defaults can be overridden under
+ * `[profile.<name>]`.
+ */
+#[deriving(Show,Clone,PartialEq)]
+pub struct Profile {
+    name: String,
+    opt_level: uint,
+    debug: bool,
+    rustc_args: Vec<String>,
+    // The directory under `target` the profile compiles into, if any
+    dest: Option<String>
+}
+
+impl Profile {
+    pub fn default_dev() -> Profile {
+        Profile {
+            name: "dev".to_str(),
+            opt_level: 0,
+            debug: true,
+            rustc_args: Vec::new(),
+            dest: None
+        }
+    }
+
+    pub fn default_release() -> Profile {
+        Profile {
+            name: "release".to_str(),
+            opt_level: 3,
+            debug: false,
+            rustc_args: Vec::new(),
+            dest: Some("release".to_str())
+        }
+    }
+
+    pub fn default_test() -> Profile {
+        Profile {
+            name: "test".to_str(),
+            opt_level: 0,
+            debug: true,
+            rustc_args: Vec::new(),
+            dest: Some("test".to_str())
+        }
+    }
+
+    pub fn default_profiles() -> Vec<Profile> {
+        vec!(Profile::default_dev(), Profile::default_release(), Profile::default_test())
+    }
+
+    pub fn opt_level(mut self, level: uint) -> Profile {
+        self.opt_level = level;
+        self
+    }
+
+    pub fn debug(mut self, debug: bool) -> Profile {
+        self.debug = debug;
+        self
+    }
+
+    pub fn rustc_args(mut self, args: Vec<String>) -> Profile {
+        self.rustc_args = args;
+        self
+    }
+
+    pub fn get_name<'a>(&'a self) -> &'a str {
+        self.name.as_slice()
+    }
+
+    pub fn get_opt_level(&self) -> uint {
+        self.opt_level
+    }
+
+    pub fn is_debug(&self) -> bool {
+        self.debug
+    }
+
+    pub fn get_rustc_args<'a>(&'a self) -> &'a [String] {
+        self.rustc_args.as_slice()
+    }
+
+    pub fn get_dest<'a>(&'a self) -> Option<&'a str> {
+        self.dest.as_ref().map(|dest| dest.as_slice())
+    }
+}
+
 #[deriving(Show,Clone,PartialEq,Encodable)]
 pub enum TargetKind {
     LibTarget(Vec<LibKind>),
@@ -93,7 +179,8 @@ pub enum TargetKind {
 pub struct Target {
     kind: TargetKind,
     name: String,
-    path: Path
+    path: Path,
+    plugin: bool
 }
 
 #[deriving(Encodable)]
@@ -125,16 +212,23 @@ impl Show for Target {
 }
 
 impl Manifest {
-    pub fn new(summary: &Summary, targets: &[Target], target_dir: &Path, sources: Vec<SourceId>) -> Manifest {
+    pub fn new(summary: &Summary, authors: &[String], targets: &[Target], target_dir: &Path, sources: Vec<SourceId>, profiles: Vec<Profile>, build: Option<String>) -> Manifest {
         Manifest {
             summary: summary.clone(),
-            authors: Vec::new(),
+            authors: Vec::from_slice(authors),
             targets: Vec::from_slice(targets),
             target_dir: target_dir.clone(),
-            sources: sources
+            sources: sources,
+            dev_sources: vec!(),
+            profiles: profiles,
+            build: build
         }
     }
 
+    pub fn dev_sources(self, dev_sources: Vec<SourceId>) -> Manifest {
+        Manifest { dev_sources: dev_sources, ..self }
+    }
+
     pub fn get_summary<'a>(&'a self) -> &'a Summary {
         &self.summary
     }
@@ -167,9 +261,41 @@ impl Manifest {
         &self.target_dir
     }
 
+    pub fn set_target_dir(&mut self, target_dir: &Path) {
+        self.target_dir = target_dir.clone();
+    }
+
     pub fn get_sources<'a>(&'a self) -> &'a [SourceId] {
         self.sources.as_slice()
     }
+
+    pub fn get_dev_sources<'a>(&'a self) -> &'a [SourceId] {
+        self.dev_sources.as_slice()
+    }
+
+    pub fn get_profiles<'a>(&'a self) -> &'a [Profile] {
+        self.profiles.as_slice()
+    }
+
+    pub fn get_profile<'a>(&'a self, name: &str) -> Option<&'a Profile> {
+        self.profiles.iter().find(|profile| profile.get_name() == name)
+    }
+
+    pub fn get_build<'a>(&'a self) -> Option<&'a str> {
+        self.build.as_ref().map(|build| build.as_slice())
+    }
+
+    pub fn serialize(&self) -> SerializedManifest {
+        SerializedManifest {
+            name: self.summary.get_name().to_str(),
+            version: self.summary.get_version().to_str(),
+            dependencies: self.summary.get_dependencies().iter().map(|d| SerializedDependency::from_dependency(d)).collect(),
+            features: self.summary.get_features().clone(),
+            authors: self.authors.clone(),
+            targets: self.targets.clone(),
+            target_dir: self.target_dir.display().to_str()
+        }
+    }
 }
 
 impl Target {
@@ -177,7 +303,8 @@ impl Target {
         Target {
             kind: LibTarget(crate_targets),
             name: name.to_str(),
-            path: path.clone()
+            path: path.clone(),
+            plugin: false
         }
     }
 
@@ -185,10 +312,24 @@ impl Target {
         Target {
             kind: BinTarget,
             name: name.to_str(),
-            path: path.clone()
+            path: path.clone(),
+            plugin: false
         }
     }
 
+    /**
+     * Marks a lib as a compiler plugin, which is always compiled for the
+     * host, even when cross-compiling.
+     */
+    pub fn plugin(mut self, plugin: bool) -> Target {
+        self.plugin = plugin;
+        self
+    }
+
+    pub fn get_name<'a>(&'a self) -> &'a str {
+        self.name.as_slice()
+    }
+
     pub fn get_path<'a>(&'a self) -> &'a Path {
         &self.path
     }
@@ -200,6 +341,10 @@ impl Target {
         }
     }
 
+    pub fn is_plugin(&self) -> bool {
+        self.plugin
+    }
+
     pub fn is_bin(&self) -> bool {
         match self.kind {
             BinTarget => true,
@@ -4,6 +4,7 @@ pub use self::registry::{
 
 pub use self::manifest::{
     Manifest,
+    Profile,
     Target,
     TargetKind,
 };
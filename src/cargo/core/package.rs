@@ -107,9 +107,26 @@ impl Package {
         self.get_root().join(self.get_target_dir())
     }
 
+    /**
+     * Compile the package into `dir` instead of its own `target`, e.g. the
+     * target directory shared by a workspace. A relative `dir` is relative
+     * to the package root.
+     */
+    pub fn set_target_dir(&mut self, dir: &Path) {
+        self.manifest.set_target_dir(dir);
+    }
+
     pub fn get_sources<'a>(&'a self) -> &'a [SourceId] {
         self.manifest.get_sources()
     }
+
+    /**
+     * The sources of the development dependencies, which are only needed
+     * to build the package's tests.
+     */
+    pub fn get_dev_sources<'a>(&'a self) -> &'a [SourceId] {
+        self.manifest.get_dev_sources()
+    }
 }
 
 impl Show for Package {
@@ -158,8 +175,11 @@ impl PackageSet {
         let mut graph = graph::Graph::new();
 
         for pkg in self.packages.iter() {
+            // Optional dependencies that were not enabled are not in the set
             let deps: Vec<&str> = pkg.get_dependencies().iter()
+                .filter(|dep| dep.is_transitive())
                 .map(|dep| dep.get_name())
+                .filter(|name| self.packages.iter().any(|other| other.get_name() == *name))
                 .collect();
 
             graph.add(pkg.get_name(), deps.as_slice());
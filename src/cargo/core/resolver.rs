@@ -7,42 +7,251 @@ use core::{
     Registry
 };
 use util::result::CargoResult;
+use util::{CargoError,simple_human};
 
 /* TODO:
  * - The correct input here is not a registry. Resolves should be performable
  * on package summaries vs. the packages themselves.
  */
 pub fn resolve<R: Registry + Show>(deps: &[Dependency], registry: &R) -> CargoResult<Vec<PackageId>> {
+    resolve_features(deps, registry).map(|resolve| resolve.ids)
+}
+
+/**
+ * The packages selected by a resolve, along with the features enabled on
+ * each of them. A package's features are the union of the ones asked for
+ * by everything that depends on it.
+ */
+#[deriving(Show,Clone)]
+pub struct Resolve {
+    ids: Vec<PackageId>,
+    features: HashMap<String, Vec<String>>
+}
+
+impl Resolve {
+    pub fn new(ids: Vec<PackageId>, features: HashMap<String, Vec<String>>) -> Resolve {
+        Resolve { ids: ids, features: features }
+    }
+
+    pub fn get_package_ids<'a>(&'a self) -> &'a [PackageId] {
+        self.ids.as_slice()
+    }
+
+    pub fn get_features<'a>(&'a self, name: &str) -> &'a [String] {
+        self.features.find_equiv(&name).map(|features| features.as_slice()).unwrap_or(&[])
+    }
+
+    /**
+     * Records the features of a package the resolver did not select, i.e.
+     * the root package.
+     */
+    pub fn set_features(&mut self, name: &str, features: Vec<String>) {
+        self.features.insert(name.to_str(), features);
+    }
+}
+
+pub fn resolve_features<R: Registry + Show>(deps: &[Dependency], registry: &R) -> CargoResult<Resolve> {
     log!(5, "resolve; deps={}; registry={}", deps, registry);
 
-    let mut remaining = Vec::from_slice(deps);
-    let mut resolve = HashMap::<&str, &Summary>::new();
+    let remaining = deps.iter().map(|dep| Requirement::root(dep)).collect();
+    let ctx = try!(activate_deps(remaining, Context::new(), registry));
+
+    let ret = Resolve {
+        ids: ctx.summaries.values().map(|summary| summary.get_package_id().clone()).collect(),
+        features: ctx.features
+    };
+
+    log!(5, "resolve complete; ret={}", ret);
+    Ok(ret)
+}
+
+/**
+ * A dependency along with the package that introduced it. Dependencies of
+ * the root package have no parent.
+ */
+#[deriving(Clone,Show)]
+struct Requirement {
+    dep: Dependency,
+    parent: Option<PackageId>
+}
+
+impl Requirement {
+    fn root(dep: &Dependency) -> Requirement {
+        Requirement { dep: dep.clone(), parent: None }
+    }
+
+    fn new(dep: &Dependency, parent: &PackageId) -> Requirement {
+        Requirement { dep: dep.clone(), parent: Some(parent.clone()) }
+    }
+
+    fn describe(&self) -> String {
+        match self.parent {
+            Some(ref parent) => format!("`{}` (required by {})", self.dep.get_version_req(), parent),
+            None => format!("`{}` (required by the root package)", self.dep.get_version_req())
+        }
+    }
+}
+
+/**
+ * The state of a partial resolution. It is cloned before every candidate is
+ * tried so that backtracking only has to drop the failed copy.
+ */
+#[deriving(Clone)]
+struct Context<'a> {
+    // The summary activated for each package name
+    summaries: HashMap<String, &'a Summary>,
+    // Every requirement placed on each package name so far
+    requirements: HashMap<String, Vec<Requirement>>,
+    // The features enabled on each package name so far
+    features: HashMap<String, Vec<String>>
+}
+
+impl<'a> Context<'a> {
+    fn new() -> Context<'a> {
+        Context { summaries: HashMap::new(), requirements: HashMap::new(), features: HashMap::new() }
+    }
+
+    fn require(&mut self, req: &Requirement) {
+        self.requirements
+            .find_or_insert_with(req.dep.get_name().to_str(), |_| Vec::new())
+            .push(req.clone());
+    }
+
+    /**
+     * Enables the features `dep` asks of `summary`, on top of the ones
+     * already enabled on it. Returns the dependencies of `summary` that are
+     * needed now but were not before.
+     */
+    fn enable_features(&mut self, summary: &'a Summary, dep: &Dependency) -> CargoResult<Vec<&'a Dependency>> {
+        let previous = self.features.find_equiv(&summary.get_name()).map(|features| features.clone());
+
+        let mut requested = Vec::from_slice(dep.get_features());
+        requested.push_all(previous.as_ref().map(|features| features.as_slice()).unwrap_or(&[]));
+
+        let enabled = try!(summary.expand_features(requested.as_slice(), dep.uses_default_features()));
+
+        // Only the root package's development dependencies are resolved
+        let added = summary.get_dependencies().iter()
+            .filter(|dep| dep.is_transitive() && dep.is_active(enabled.as_slice()))
+            .filter(|dep| previous.as_ref().map(|features| !dep.is_active(features.as_slice())).unwrap_or(true))
+            .collect();
+
+        self.features.insert(summary.get_name().to_str(), enabled);
+        Ok(added)
+    }
+
+    fn describe_requirements(&self, name: &str) -> String {
+        let reqs: Vec<String> = self.requirements.find_equiv(&name)
+            .map(|reqs| reqs.iter().map(|r| format!("  {}", r.describe())).collect())
+            .unwrap_or_else(|| Vec::new());
+
+        reqs.connect("\n")
+    }
+}
+
+fn activate_deps<'a, R: Registry>(mut remaining: Vec<Requirement>, mut ctx: Context<'a>, registry: &'a R) -> CargoResult<Context<'a>> {
+    let curr = match remaining.pop() {
+        Some(curr) => curr,
+        None => return Ok(ctx)
+    };
+
+    let name = curr.dep.get_name().to_str();
+    ctx.require(&curr);
+
+    // If a version was already picked, it has to satisfy this requirement as
+    // well, otherwise an earlier choice must be revisited
+    let existing = ctx.summaries.find(&name).map(|summary| *summary);
 
-    loop {
-        let curr = match remaining.pop() {
-            Some(curr) => curr,
-            None => {
-                let ret = resolve.values().map(|summary| summary.get_package_id().clone()).collect();
-                log!(5, "resolve complete; ret={}", ret);
-                return Ok(ret);
+    match existing {
+        Some(summary) => {
+            if curr.dep.get_version_req().matches(summary.get_version()) {
+                for dep in try!(ctx.enable_features(summary, &curr.dep)).iter() {
+                    remaining.push(Requirement::new(*dep, summary.get_package_id()));
+                }
+
+                return activate_deps(remaining, ctx, registry);
             }
-        };
 
-        let opts = registry.query(curr.get_name());
+            return Err(conflict_error(&ctx, name.as_slice(), summary));
+        }
+        None => ()
+    }
+
+    let all = registry.query(name.as_slice());
+
+    let mut candidates: Vec<&'a Summary> = all.iter()
+        .filter(|summary| curr.dep.get_version_req().matches(summary.get_version()))
+        .map(|summary| *summary)
+        .collect();
+
+    if candidates.is_empty() {
+        return Err(no_match_error(&curr, all.as_slice()));
+    }
+
+    candidates.sort_by(|a, b| newest_first(a.get_package_id(), b.get_package_id()));
+
+    let mut last_err = None;
+
+    for candidate in candidates.iter() {
+        log!(5, "activating; pkg={}", candidate.get_package_id());
+
+        let mut ctx = ctx.clone();
+        let mut remaining = remaining.clone();
 
-        assert!(opts.len() > 0, "no matches for {}", curr.get_name());
-        // Temporary, but we must have exactly one option to satisfy the dep
-        assert!(opts.len() == 1, "invalid num of results {}", opts.len());
+        ctx.summaries.insert(name.clone(), *candidate);
 
-        let pkg = opts.get(0);
-        resolve.insert(pkg.get_name(), *pkg);
+        let added = match ctx.enable_features(*candidate, &curr.dep) {
+            Ok(added) => added,
+            Err(err) => {
+                last_err = Some(err);
+                continue;
+            }
+        };
+
+        for dep in added.iter() {
+            remaining.push(Requirement::new(*dep, candidate.get_package_id()));
+        }
 
-        for dep in pkg.get_dependencies().iter() {
-            if !resolve.contains_key_equiv(&dep.get_name()) {
-                remaining.push(dep.clone());
+        match activate_deps(remaining, ctx, registry) {
+            Ok(ctx) => return Ok(ctx),
+            Err(err) => {
+                log!(5, "backtracking; pkg={}; err={}", candidate.get_package_id(), err);
+                last_err = Some(err);
             }
         }
     }
+
+    Err(last_err.unwrap())
+}
+
+fn newest_first(a: &PackageId, b: &PackageId) -> Ordering {
+    let (a, b) = (a.get_version(), b.get_version());
+
+    if a > b {
+        Less
+    } else if a < b {
+        Greater
+    } else {
+        Equal
+    }
+}
+
+fn conflict_error(ctx: &Context, name: &str, selected: &Summary) -> CargoError {
+    simple_human(format!("Conflicting requirements for `{}`; {} was selected, but the following were required:\n{}",
+                         name, selected.get_package_id(), ctx.describe_requirements(name)))
+}
+
+fn no_match_error(req: &Requirement, all: &[&Summary]) -> CargoError {
+    let name = req.dep.get_name();
+
+    if all.is_empty() {
+        return simple_human(format!("No package named `{}` found; {}", name, req.describe()));
+    }
+
+    let versions: Vec<String> = all.iter().map(|summary| summary.get_version().to_str()).collect();
+
+    simple_human(format!("No version of `{}` matches {}; available versions: {}",
+                         name, req.describe(), versions.connect(", ")))
 }
 
 #[cfg(test)]
@@ -59,8 +268,11 @@ mod test {
         Summary
     };
 
+    use std::collections::HashMap;
+
     use super::{
-        resolve
+        resolve,
+        resolve_features
     };
 
     macro_rules! pkg(
@@ -80,10 +292,33 @@ mod test {
         Summary::new(&PackageId::new(name, "1.0.0", "http://www.example.com/"), &[])
     }
 
+    fn pkg_version(name: &str, version: &str, deps: &[Dependency]) -> Summary {
+        Summary::new(&PackageId::new(name, version, "http://www.example.com/"), deps)
+    }
+
     fn dep(name: &str) -> Dependency {
         Dependency::parse(name, "1.0.0").unwrap()
     }
 
+    fn dep_req(name: &str, req: &str) -> Dependency {
+        Dependency::parse(name, req).unwrap()
+    }
+
+    fn optional_dep(name: &str) -> Dependency {
+        dep(name).optional(true)
+    }
+
+    // Each feature's members are separated by spaces
+    fn features(features: &[(&str, &str)]) -> HashMap<String, Vec<String>> {
+        features.iter()
+            .map(|&(name, enabled)| (name.to_str(), enabled.words().map(|s| s.to_str()).collect()))
+            .collect()
+    }
+
+    fn id(name: &str, version: &str) -> PackageId {
+        PackageId::new(name, version, "http://www.example.com/")
+    }
+
     fn registry(pkgs: Vec<Summary>) -> Vec<Summary> {
         pkgs
     }
@@ -140,4 +375,97 @@ mod test {
 
         assert_that(&res, contains(names(["foo", "bar"])));
     }
+
+    #[test]
+    pub fn test_resolving_picks_newest_matching_version() {
+        let reg = registry(vec!(
+            pkg_version("foo", "1.0.0", []),
+            pkg_version("foo", "1.1.0", []),
+            pkg_version("foo", "2.0.0", [])));
+
+        let res = resolve([dep_req("foo", "< 2.0.0")], &reg).unwrap();
+
+        assert_that(&res, equal_to(&vec!(id("foo", "1.1.0"))));
+    }
+
+    #[test]
+    pub fn test_resolving_backtracks_on_conflict() {
+        let reg = registry(vec!(
+            pkg_version("foo", "1.0.0", []),
+            pkg_version("foo", "2.0.0", []),
+            pkg_version("bar", "1.0.0", [dep_req("foo", "1.0.0")]),
+            pkg_version("bar", "2.0.0", [dep_req("foo", "2.0.0")]),
+            pkg_version("baz", "1.0.0", [dep_req("foo", "1.0.0")])));
+
+        let res = resolve([dep_req("bar", ">= 1.0.0"), dep_req("baz", "1.0.0")], &reg).unwrap();
+
+        assert_that(&res, contains(vec!(id("foo", "1.0.0"), id("bar", "1.0.0"), id("baz", "1.0.0"))).exactly());
+    }
+
+    #[test]
+    pub fn test_resolving_conflict_names_requirements() {
+        let reg = registry(vec!(
+            pkg_version("foo", "1.0.0", []),
+            pkg_version("foo", "2.0.0", []),
+            pkg_version("bar", "1.0.0", [dep_req("foo", "2.0.0")])));
+
+        let err = resolve([dep_req("foo", "1.0.0"), dep("bar")], &reg).unwrap_err();
+
+        assert!(err.get_desc().contains("Conflicting requirements for `foo`"), "{}", err);
+        assert!(err.get_desc().contains("required by bar v1.0.0"), "{}", err);
+        assert!(err.get_desc().contains("required by the root package"), "{}", err);
+    }
+
+    #[test]
+    pub fn test_resolving_with_no_matching_version() {
+        let reg = registry(vec!(pkg_version("foo", "1.0.0", [])));
+
+        let err = resolve([dep_req("foo", ">= 2.0.0")], &reg).unwrap_err();
+
+        assert_that(err.get_desc(), equal_to("No version of `foo` matches `>= 2.0.0` (required by the root package); available versions: 1.0.0"));
+    }
+
+    #[test]
+    pub fn test_resolving_skips_disabled_optional_deps() {
+        let reg = registry(vec!(pkg_version("foo", "1.0.0", [optional_dep("bar")]), pkg("bar")));
+        let res = resolve([dep("foo")], &reg).unwrap();
+
+        assert_that(&res, equal_to(&names(["foo"])));
+    }
+
+    #[test]
+    pub fn test_resolving_optional_deps_enabled_by_default() {
+        let reg = registry(vec!(
+            pkg_version("foo", "1.0.0", [optional_dep("bar")]).features(features([("default", "bar")])),
+            pkg("bar")));
+
+        let res = resolve([dep("foo")], &reg).unwrap();
+        assert_that(&res, contains(names(["foo", "bar"])).exactly());
+
+        let res = resolve([dep("foo").default_features(false)], &reg).unwrap();
+        assert_that(&res, equal_to(&names(["foo"])));
+    }
+
+    #[test]
+    pub fn test_resolving_unifies_features() {
+        let reg = registry(vec!(
+            pkg_version("foo", "1.0.0", [optional_dep("bar")]).features(features([("tls", "bar"), ("gzip", "")])),
+            pkg("bar"),
+            pkg_version("baz", "1.0.0", [dep("foo").features(vec!("gzip".to_str()))]),
+            pkg_version("qux", "1.0.0", [dep("foo").features(vec!("tls".to_str()))])));
+
+        let res = resolve_features([dep("baz"), dep("qux")], &reg).unwrap();
+
+        assert_that(&Vec::from_slice(res.get_package_ids()), contains(names(["foo", "bar", "baz", "qux"])).exactly());
+        assert_eq!(Vec::from_slice(res.get_features("foo")), vec!("bar".to_str(), "gzip".to_str(), "tls".to_str()));
+    }
+
+    #[test]
+    pub fn test_resolving_with_unknown_feature() {
+        let reg = registry(vec!(pkg("foo")));
+
+        let err = resolve([dep("foo").features(vec!("tls".to_str()))], &reg).unwrap_err();
+
+        assert_that(err.get_desc(), equal_to("Package `foo` does not have the feature `tls`"));
+    }
 }
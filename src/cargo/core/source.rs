@@ -1,5 +1,6 @@
 use url::Url;
 use core::{Summary,Package,PackageId};
+use sources::git::GitReference;
 use util::CargoResult;
 
 /**
@@ -39,7 +40,9 @@ pub trait Source {
 
 #[deriving(Clone,PartialEq)]
 pub enum SourceKind {
-    GitKind(String)
+    GitKind(GitReference),
+    // A directory on the local file system, named by a `file://` URL
+    PathKind
 }
 
 #[deriving(Clone,PartialEq)]
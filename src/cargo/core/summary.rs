@@ -1,23 +1,33 @@
+use std::collections::HashMap;
 use semver::Version;
 use core::{
     Dependency,
     PackageId
 };
+use util::{CargoResult,simple_human};
 
 #[deriving(Show,Clone,PartialEq)]
 pub struct Summary {
     package_id: PackageId,
-    dependencies: Vec<Dependency>
+    dependencies: Vec<Dependency>,
+    // Each feature, with the features and optional dependencies it enables
+    features: HashMap<String, Vec<String>>
 }
 
 impl Summary {
     pub fn new(pkg_id: &PackageId, dependencies: &[Dependency]) -> Summary {
         Summary {
             package_id: pkg_id.clone(),
-            dependencies: Vec::from_slice(dependencies)
+            dependencies: Vec::from_slice(dependencies),
+            features: HashMap::new()
         }
     }
 
+    pub fn features(mut self, features: HashMap<String, Vec<String>>) -> Summary {
+        self.features = features;
+        self
+    }
+
     pub fn get_package_id<'a>(&'a self) -> &'a PackageId {
         &self.package_id
     }
@@ -33,6 +43,50 @@ impl Summary {
     pub fn get_dependencies<'a>(&'a self) -> &'a [Dependency] {
         self.dependencies.as_slice()
     }
+
+    pub fn get_features<'a>(&'a self) -> &'a HashMap<String, Vec<String>> {
+        &self.features
+    }
+
+    /**
+     * Expands the requested features, plus the `default` feature unless
+     * `use_default` is false, into every feature they enable directly or
+     * through other features. An optional dependency is enabled by the
+     * feature named after it. The result is sorted.
+     */
+    pub fn expand_features(&self, requested: &[String], use_default: bool) -> CargoResult<Vec<String>> {
+        let mut remaining = Vec::from_slice(requested);
+        let mut ret: Vec<String> = Vec::new();
+
+        if use_default && self.features.contains_key_equiv(&"default") {
+            remaining.push("default".to_str());
+        }
+
+        loop {
+            let curr = match remaining.pop() {
+                Some(curr) => curr,
+                None => break
+            };
+
+            if ret.contains(&curr) {
+                continue;
+            }
+
+            match self.features.find(&curr) {
+                Some(enabled) => remaining.push_all(enabled.as_slice()),
+                None => {
+                    if !self.dependencies.iter().any(|dep| dep.is_optional() && dep.get_name() == curr.as_slice()) {
+                        return Err(simple_human(format!("Package `{}` does not have the feature `{}`", self.get_name(), curr)));
+                    }
+                }
+            }
+
+            ret.push(curr);
+        }
+
+        ret.sort();
+        Ok(ret)
+    }
 }
 
 pub trait SummaryVec {
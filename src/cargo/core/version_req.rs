@@ -44,7 +44,10 @@ impl VersionReq {
                 Sigil(x) => try!(builder.set_sigil(x)),
                 AlphaNum(x) => try!(builder.set_version_part(x)),
                 Dot => (), // Nothing to do for now
-                _ => unimplemented!()
+                Comma => {
+                    predicates.push(try!(builder.build()));
+                    builder = PredBuilder::new();
+                }
             }
         }
 
@@ -81,7 +84,8 @@ impl Predicate {
             Ex => self.is_exact(ver),
             Gt => self.is_greater(ver),
             GtEq => self.is_exact(ver) || self.is_greater(ver),
-            _ => false // not implemented
+            Lt => self.is_less(ver),
+            LtEq => self.is_exact(ver) || self.is_less(ver)
         }
     }
 
@@ -111,15 +115,20 @@ impl Predicate {
         true
     }
 
+    /**
+     * Returns true if `ver` is strictly greater than the version described
+     * by the predicate. Missing minor or patch components are treated as
+     * wildcards, so `> 1` is not satisfied by any `1.x.y`.
+     */
     fn is_greater(self, ver: &Version) -> bool {
         if self.major != ver.major {
-            return self.major > ver.major;
+            return ver.major > self.major;
         }
 
         match self.minor {
             Some(minor) => {
                 if minor != ver.minor {
-                    return minor > ver.minor
+                    return ver.minor > minor
                 }
             }
             None => return false
@@ -128,7 +137,38 @@ impl Predicate {
         match self.patch {
             Some(patch) => {
                 if patch != ver.patch {
-                    return patch > ver.patch
+                    return ver.patch > patch
+                }
+            }
+
+            None => return false
+        }
+
+        false
+    }
+
+    /**
+     * Returns true if `ver` is strictly less than the version described by
+     * the predicate, with the same wildcard rules as `is_greater`.
+     */
+    fn is_less(self, ver: &Version) -> bool {
+        if self.major != ver.major {
+            return ver.major < self.major;
+        }
+
+        match self.minor {
+            Some(minor) => {
+                if minor != ver.minor {
+                    return ver.minor < minor
+                }
+            }
+            None => return false
+        }
+
+        match self.patch {
+            Some(patch) => {
+                if patch != ver.patch {
+                    return ver.patch < patch
                 }
             }
 
@@ -500,7 +540,38 @@ mod test {
 
         assert!(r.to_str() == ">= 1.0.0".to_str());
 
-        assert_match(&r, ["1.0.0"]);
+        assert_match(&r, ["1.0.0", "1.0.1", "1.2.0", "2.0.0"]);
+        assert_not_match(&r, ["0.9.9", "0.1.0"]);
+
+        let r = req("> 1.0.0");
+
+        assert_match(&r, ["1.0.1", "1.1.0", "2.0.0"]);
+        assert_not_match(&r, ["1.0.0", "0.9.9"]);
+    }
+
+    #[test]
+    pub fn test_parsing_less_than() {
+        let r = req("< 2.0.0");
+
+        assert!(r.to_str() == "< 2.0.0".to_str());
+
+        assert_match(&r, ["1.9.9", "1.0.0", "0.1.0"]);
+        assert_not_match(&r, ["2.0.0", "2.0.1", "3.0.0"]);
+
+        let r = req("<= 2.0.0");
+
+        assert_match(&r, ["2.0.0", "1.9.9"]);
+        assert_not_match(&r, ["2.0.1", "3.0.0"]);
+    }
+
+    #[test]
+    pub fn test_parsing_multiple_predicates() {
+        let r = req(">= 1.2.0, < 1.3.5");
+
+        assert!(r.to_str() == ">= 1.2.0, < 1.3.5".to_str());
+
+        assert_match(&r, ["1.2.0", "1.3.0", "1.3.4"]);
+        assert_not_match(&r, ["1.1.9", "1.3.5", "2.0.0"]);
     }
 
     /* TODO:
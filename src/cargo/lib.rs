@@ -20,6 +20,7 @@ extern crate hamcrest;
 use serialize::{Decoder,Encoder,Decodable,Encodable,json};
 use std::io;
 use hammer::{FlagDecoder,FlagConfig,HammerError};
+use util::usage::{Usage,FlagCollector};
 pub use core::errors::{CLIError,CLIResult,ToResult};
 
 macro_rules! some(
@@ -35,8 +36,8 @@ pub mod ops;
 pub mod sources;
 pub mod util;
 
-trait RepresentsFlags : FlagConfig + Decodable<FlagDecoder, HammerError> {}
-impl<T: FlagConfig + Decodable<FlagDecoder, HammerError>> RepresentsFlags for T {}
+trait RepresentsFlags : FlagConfig + Decodable<FlagDecoder, HammerError> + Decodable<FlagCollector, ()> {}
+impl<T: FlagConfig + Decodable<FlagDecoder, HammerError> + Decodable<FlagCollector, ()>> RepresentsFlags for T {}
 
 trait RepresentsJSON : Decodable<json::Decoder, json::DecoderError> {}
 impl <T: Decodable<json::Decoder, json::DecoderError>> RepresentsJSON for T {}
@@ -46,25 +47,35 @@ pub struct NoFlags;
 
 impl FlagConfig for NoFlags {}
 
-pub fn execute_main<'a, T: RepresentsFlags, U: RepresentsJSON, V: Encodable<json::Encoder<'a>, io::IoError>>(exec: fn(T, U) -> CLIResult<Option<V>>) {
-    fn call<'a, T: RepresentsFlags, U: RepresentsJSON, V: Encodable<json::Encoder<'a>, io::IoError>>(exec: fn(T, U) -> CLIResult<Option<V>>) -> CLIResult<Option<V>> {
-        let flags = try!(flags_from_args::<T>());
+/**
+  `about` is the one-line description of the command shown by `--help`,
+  along with the flags declared by `T`.
+*/
+pub fn execute_main<'a, T: RepresentsFlags, U: RepresentsJSON, V: Encodable<json::Encoder<'a>, io::IoError>>(exec: fn(T, U) -> CLIResult<Option<V>>, about: &str) {
+    fn call<'a, T: RepresentsFlags, U: RepresentsJSON, V: Encodable<json::Encoder<'a>, io::IoError>>(exec: fn(T, U) -> CLIResult<Option<V>>, about: &str) -> CLIResult<Option<V>> {
+        let flags = match try!(flags_from_args::<T>(about)) {
+            Some(flags) => flags,
+            None => return Ok(None)
+        };
         let json = try!(json_from_stdin::<U>());
 
         exec(flags, json)
     }
 
-    process_executed(call(exec))
+    process_executed(call(exec, about))
 }
 
-pub fn execute_main_without_stdin<'a, T: RepresentsFlags, V: Encodable<json::Encoder<'a>, io::IoError>>(exec: fn(T) -> CLIResult<Option<V>>) {
-    fn call<'a, T: RepresentsFlags, V: Encodable<json::Encoder<'a>, io::IoError>>(exec: fn(T) -> CLIResult<Option<V>>) -> CLIResult<Option<V>> {
-        let flags = try!(flags_from_args::<T>());
+pub fn execute_main_without_stdin<'a, T: RepresentsFlags, V: Encodable<json::Encoder<'a>, io::IoError>>(exec: fn(T) -> CLIResult<Option<V>>, about: &str) {
+    fn call<'a, T: RepresentsFlags, V: Encodable<json::Encoder<'a>, io::IoError>>(exec: fn(T) -> CLIResult<Option<V>>, about: &str) -> CLIResult<Option<V>> {
+        let flags = match try!(flags_from_args::<T>(about)) {
+            Some(flags) => flags,
+            None => return Ok(None)
+        };
 
         exec(flags)
     }
 
-    process_executed(call(exec));
+    process_executed(call(exec, about));
 }
 
 pub fn process_executed<'a, T: Encodable<json::Encoder<'a>, io::IoError>>(result: CLIResult<Option<T>>) {
@@ -93,9 +104,49 @@ fn args() -> Vec<String> {
     std::os::args()
 }
 
-fn flags_from_args<T: RepresentsFlags>() -> CLIResult<T> {
-    let mut decoder = FlagDecoder::new::<T>(args().tail());
-    Decodable::decode(&mut decoder).to_result(|e: HammerError| CLIError::new(e.message, None::<&str>, 1))
+/**
+  The name the user invoked the command by: `cargo-compile` is `cargo
+  compile`, and a builtin such as `cargo config-list` keeps its subcommand.
+*/
+fn command_name() -> String {
+    let args = args();
+    let program = Path::new(args.get(0).as_slice());
+    let program = program.filename_str().unwrap_or("cargo");
+
+    if program.starts_with("cargo-") {
+        format!("cargo {}", program.slice_from("cargo-".len()))
+    } else if program == "cargo" && args.len() > 1 {
+        format!("cargo {}", args.get(1))
+    } else {
+        program.to_str()
+    }
+}
+
+/**
+  Decodes the flags for a command, or prints its usage and returns `None`
+  when it was passed `--help`. Flags it does not declare are usage errors.
+*/
+fn flags_from_args<T: RepresentsFlags>(about: &str) -> CLIResult<Option<T>> {
+    let args = args();
+    let args = args.tail();
+    let usage = Usage::new::<T>(command_name().as_slice(), about);
+
+    // Arguments after `--` belong to something else, e.g. the program that
+    // `cargo run` runs
+    if args.iter().take_while(|arg| arg.as_slice() != "--").any(|arg| arg.as_slice() == "--help") {
+        print!("{}", usage);
+        return Ok(None);
+    }
+
+    match usage.unknown_flag(args) {
+        Some(flag) => return Err(CLIError::new(format!("Unknown flag `{}`\n\n{}", flag, usage), None::<&str>, 1)),
+        None => ()
+    }
+
+    let mut decoder = FlagDecoder::new::<T>(args);
+    Decodable::decode(&mut decoder)
+        .map(|flags| Some(flags))
+        .to_result(|e: HammerError| CLIError::new(format!("{}\n\n{}", e.message, usage), None::<&str>, 1))
 }
 
 fn json_from_stdin<T: RepresentsJSON>() -> CLIResult<T> {
@@ -0,0 +1,242 @@
+use std::io;
+use std::io::File;
+use std::str;
+use core::Package;
+use ops::cargo_rustc::rustc_to_cargo_err;
+use util;
+use util::{CargoResult,CargoError,ProcessBuilder,io_error,human_error,simple_human};
+use util::result::ProcessError;
+
+/**
+ * A package's build script runs before the package is compiled, e.g. to
+ * build a C library it wraps. The `build` key in `[project]` names either a
+ * Rust file, which is compiled for the host and run, or a command.
+ *
+ * The script runs in the package root with these environment variables:
+ *
+ *   CARGO_MANIFEST_DIR   the package root
+ *   OUT_DIR              a directory for the script's output
+ *   TARGET               the triple the package is compiled for
+ *   PROFILE              the name of the profile the package is compiled with
+ *
+ * Lines of its stdout of the form `cargo:rustc-flags=-L <dir> -l <lib>` add
+ * native search paths and libraries to the package's rustc invocations. The
+ * search paths are passed on to every package depending on it.
+ *
+ * Each script keeps its files in `build/<package>` inside the target
+ * directory, including its last stdout in `output`.
+ */
+pub struct BuildScript {
+    pkg: String,
+    root: Path,
+    dir: Path,
+    compile: Option<ProcessBuilder>,
+    command: ProcessBuilder
+}
+
+static DIRECTIVE: &'static str = "cargo:rustc-flags=";
+
+#[deriving(PartialEq,Clone,Show)]
+pub struct BuildOutput {
+    search_paths: Vec<String>,
+    libs: Vec<String>
+}
+
+impl BuildScript {
+    pub fn new(pkg: &Package, build: &str, dir: &Path, triple: &str, profile: &str) -> BuildScript {
+        let root = pkg.get_root();
+        let out_dir = dir.join("out");
+
+        let (compile, command) = if build.ends_with(".rs") {
+            let exe = dir.join("build-script");
+
+            let rustc = util::process("rustc")
+                .cwd(root.clone())
+                .args([build.to_str(), "-o".to_str(), exe.display().to_str()])
+                .env("RUST_LOG", None);
+
+            (Some(rustc), util::process(exe.display().to_str().as_slice()))
+        } else {
+            let mut words = build.words();
+            let program = words.next().unwrap_or("");
+            let args: Vec<&str> = words.collect();
+
+            (None, util::process(program).args(args.as_slice()))
+        };
+
+        let command = command
+            .cwd(root.clone())
+            .env("CARGO_MANIFEST_DIR", Some(root.display().to_str().as_slice()))
+            .env("OUT_DIR", Some(out_dir.display().to_str().as_slice()))
+            .env("TARGET", Some(triple))
+            .env("PROFILE", Some(profile));
+
+        BuildScript {
+            pkg: pkg.to_str(),
+            root: root,
+            dir: dir.clone(),
+            compile: compile,
+            command: command
+        }
+    }
+
+    /**
+     * Compiles the script if needed and runs it. Its stdout is saved so
+     * that packages depending on this one can find it when this one is
+     * fresh.
+     */
+    pub fn run(&self) -> CargoResult<BuildOutput> {
+        try!(io::fs::mkdir_recursive(&self.dir.join("out"), io::UserRWX).map_err(|_|
+            simple_human(format!("Could not create `{}`", self.dir.display()))));
+
+        match self.compile {
+            Some(ref rustc) => {
+                try!(rustc.exec_with_output().map_err(|e| self.compile_err(rustc, e)));
+            },
+            None => ()
+        }
+
+        let output = try!(self.command.exec_with_output().map_err(|e| self.build_script_err(e)));
+        let stdout = str::from_utf8_lossy(output.output.as_slice()).to_str();
+
+        let mut file = try!(File::create(&self.dir.join("output")).map_err(io_error));
+        try!(file.write_str(stdout.as_slice()).map_err(io_error));
+
+        BuildOutput::parse(stdout.as_slice(), self.pkg.as_slice())
+    }
+
+    fn compile_err(&self, rustc: &ProcessBuilder, err: CargoError) -> CargoError {
+        match err {
+            CargoError { kind: ProcessError(..), .. } => rustc_to_cargo_err(rustc.get_args().as_slice(), &self.root, err),
+            // rustc could not be started at all
+            _ => {
+                let msg = format!("failed to compile the build script for {}: {} could not be started", self.pkg, rustc);
+                human_error(msg, format!("root={}", self.root.display()), err)
+            }
+        }
+    }
+
+    fn build_script_err(&self, err: CargoError) -> CargoError {
+        let msg = {
+            let output = match err {
+                CargoError { kind: ProcessError(_, ref output), .. } => output,
+                // The script's program could not be started at all
+                _ => {
+                    let msg = format!("failed to run the build script for {}: {} could not be started", self.pkg, self.command);
+                    return human_error(msg, format!("root={}", self.root.display()), err);
+                }
+            };
+
+            let mut msg = format!("failed to run the build script for {}: {}", self.pkg, self.command);
+
+            output.as_ref().map(|o| {
+                let second = format!("; Error:\n{}{}",
+                                     str::from_utf8_lossy(o.output.as_slice()),
+                                     str::from_utf8_lossy(o.error.as_slice()));
+                msg.push_str(second.as_slice());
+            });
+
+            msg
+        };
+
+        human_error(msg, format!("root={}", self.root.display()), err)
+    }
+}
+
+impl BuildOutput {
+    /**
+     * Reads the output saved by the last run of the build script in `dir`,
+     * if it ran.
+     */
+    pub fn load(dir: &Path, pkg: &str) -> CargoResult<Option<BuildOutput>> {
+        let path = dir.join("output");
+
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let stdout = try!(File::open(&path).read_to_str().map_err(io_error));
+        BuildOutput::parse(stdout.as_slice(), pkg).map(|output| Some(output))
+    }
+
+    pub fn parse(stdout: &str, pkg: &str) -> CargoResult<BuildOutput> {
+        let mut ret = BuildOutput { search_paths: Vec::new(), libs: Vec::new() };
+
+        for line in stdout.lines() {
+            let line = line.trim();
+
+            if !line.starts_with(DIRECTIVE) {
+                continue;
+            }
+
+            let mut words = line.slice_from(DIRECTIVE.len()).words();
+
+            loop {
+                let (flag, value) = match (words.next(), words.next()) {
+                    (None, _) => break,
+                    (Some(flag), Some(value)) => (flag, value),
+                    (Some(flag), None) => {
+                        return Err(simple_human(format!("The build script for {} printed `{}` without a value", pkg, flag)));
+                    }
+                };
+
+                match flag {
+                    "-L" => ret.search_paths.push(value.to_str()),
+                    "-l" => ret.libs.push(value.to_str()),
+                    _ => return Err(simple_human(format!("The build script for {} printed `{}`; only `-L` and `-l` are supported in `cargo:rustc-flags`", pkg, flag)))
+                }
+            }
+        }
+
+        Ok(ret)
+    }
+
+    /**
+     * The flags for compiling the package the script belongs to.
+     */
+    pub fn rustc_args(&self) -> Vec<String> {
+        let mut ret = self.search_path_args();
+
+        for lib in self.libs.iter() {
+            ret.push("-l".to_str());
+            ret.push(lib.clone());
+        }
+
+        ret
+    }
+
+    /**
+     * The flags for compiling packages that depend on the script's package,
+     * which only need to find the native libraries when linking.
+     */
+    pub fn search_path_args(&self) -> Vec<String> {
+        let mut ret = Vec::new();
+
+        for path in self.search_paths.iter() {
+            ret.push("-L".to_str());
+            ret.push(path.clone());
+        }
+
+        ret
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BuildOutput;
+
+    #[test]
+    pub fn test_parse_build_output() {
+        let output = BuildOutput::parse("building foo\ncargo:rustc-flags=-L /tmp/foo -l foo\ncargo:rustc-flags=-l bar\n", "foo").unwrap();
+
+        assert_eq!(output.rustc_args(), vec!("-L".to_str(), "/tmp/foo".to_str(), "-l".to_str(), "foo".to_str(), "-l".to_str(), "bar".to_str()));
+        assert_eq!(output.search_path_args(), vec!("-L".to_str(), "/tmp/foo".to_str()));
+    }
+
+    #[test]
+    pub fn test_parse_build_output_with_unsupported_flag() {
+        let err = BuildOutput::parse("cargo:rustc-flags=--cfg foo\n", "foo").unwrap_err();
+
+        assert_eq!(err.get_desc(), "The build script for foo printed `--cfg`; only `-L` and `-l` are supported in `cargo:rustc-flags`");
+    }
+}
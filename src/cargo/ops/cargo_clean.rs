@@ -0,0 +1,152 @@
+/**
+ * Cargo clean removes what compiling a package left in its target
+ * directory:
+ *
+ *   target/                 everything, by default
+ *   target/release          with `--release`
+ *   target/<triple>         with `--target`, combined with `--release`
+ *                           as `target/<triple>/release`
+ *
+ * With `--package`, only the files of one dependency are removed from that
+ * directory: its libraries in `deps`, its fingerprints and the files of its
+ * build script.
+ */
+
+use std::io::fs;
+use ops;
+use util::{CargoResult,io_error,simple_human,realpath};
+use util::important_paths::find_workspace_root;
+
+pub struct CleanOptions<'a> {
+    pub package: Option<&'a str>,
+    pub release: bool,
+    pub target: Option<&'a str>
+}
+
+pub fn clean(manifest_path: &Path, opts: &CleanOptions) -> CargoResult<()> {
+    log!(4, "clean; manifest-path={}; package={}; release={}; target={}",
+         manifest_path.display(), opts.package, opts.release, opts.target);
+
+    // A workspace root that is not a package cleans the shared directory
+    let pkg = match try!(ops::Workspace::find(manifest_path)) {
+        Some(ref ws) if ws.is_virtual() && ws.get_manifest_path() == *manifest_path => try!(ws.to_package([], [], true)),
+        _ => try!(ops::read_member(manifest_path))
+    };
+
+    let mut options = ops::CompileOptions::new();
+    options.target = opts.target.map(|triple| triple.to_str());
+
+    if opts.release {
+        options.profile = "release".to_str();
+    }
+
+    // The members of a workspace share the target directory at its root
+    let root = match try!(find_workspace_root(&pkg.get_root())) {
+        Some(root) => root,
+        None => pkg.get_root()
+    };
+
+    let root = try!(realpath(&root).map_err(io_error));
+    let dir = try!(ops::output_dir(&pkg, &options));
+    try!(check_inside_root(&root, &dir));
+
+    match opts.package {
+        Some(name) => try!(check_package_name(name)),
+        None => ()
+    }
+
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    match opts.package {
+        Some(name) => clean_package(&root, &dir, name),
+        None => rm(&root, &dir)
+    }
+}
+
+/**
+ * The target directory can be configured to be anywhere, but nothing
+ * outside of the project is ever removed. Paths are compared once their
+ * symlinks and `..` components are resolved, and `root` must already be.
+ */
+fn check_inside_root(root: &Path, path: &Path) -> CargoResult<()> {
+    let real = try!(realpath(path).map_err(io_error));
+
+    if !root.is_ancestor_of(&real) || *root == real {
+        return Err(simple_human(format!("Refusing to remove `{}`, which is outside of the project root `{}`",
+                                        path.display(), root.display())));
+    }
+
+    Ok(())
+}
+
+/**
+ * The package name ends up in the paths that are removed, so it may not
+ * name a directory of its own.
+ */
+fn check_package_name(name: &str) -> CargoResult<()> {
+    if name.is_empty() || name == "." || name == ".." || name.contains_char('/') || name.contains_char('\\') {
+        return Err(simple_human(format!("`{}` is not a valid package name", name)));
+    }
+
+    Ok(())
+}
+
+fn clean_package(root: &Path, dir: &Path, name: &str) -> CargoResult<()> {
+    let fingerprints = dir.join(".fingerprint");
+    let prefix = format!("{}-", name);
+
+    // A package's fingerprints are named `<package>-<target>-<kind>`, which
+    // gives the names of the libraries it compiled to
+    let mut libs = vec!(name.to_str());
+
+    for path in try!(readdir(&fingerprints)).iter() {
+        let file = path.filename_str().unwrap_or("");
+
+        if !file.starts_with(prefix.as_slice()) {
+            continue;
+        }
+
+        if file.ends_with("-lib") {
+            libs.push(file.slice(prefix.len(), file.len() - "-lib".len()).to_str());
+        }
+
+        try!(rm(root, path));
+    }
+
+    for path in try!(readdir(&dir.join("deps"))).iter() {
+        let file = path.filename_str().unwrap_or("");
+
+        if libs.iter().any(|lib| file.starts_with(format!("lib{}-", lib).as_slice())) {
+            try!(rm(root, path));
+        }
+    }
+
+    let build = dir.join("build").join(name);
+
+    if build.exists() {
+        try!(rm(root, &build));
+    }
+
+    Ok(())
+}
+
+fn readdir(dir: &Path) -> CargoResult<Vec<Path>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    fs::readdir(dir).map_err(io_error)
+}
+
+fn rm(root: &Path, path: &Path) -> CargoResult<()> {
+    log!(5, "removing; path={}", path.display());
+    try!(check_inside_root(root, path));
+
+    if path.is_dir() {
+        fs::rmdir_recursive(path).map_err(io_error)
+    } else {
+        fs::unlink(path).map_err(io_error)
+    }
+}
@@ -16,65 +16,252 @@
 
 use std::io::MemWriter;
 use std::os;
+use std::collections::HashMap;
 use std::result;
 use std::hash::sip::SipHasher;
 use std::hash::Hasher;
 use serialize::hex::ToHex;
+use url;
 use url::Url;
 use util::config;
 use util::config::{ConfigValue};
-use core::{Package,PackageSet,Source,SourceSet};
-use core::resolver::resolve;
-use core::source::{GitKind,SourceId};
-use sources::{PathSource,GitSource};
-use sources::git::GitRemote;
+use core::{Dependency,Package,PackageSet,Source,SourceSet,Summary};
+use core::resolver::{Resolve,resolve_features};
+use core::source::{GitKind,PathKind,SourceId};
+use sources::{PathSource,GitSource,RegistrySource};
+use sources::path;
+use sources::git::{GitRemote,GitReference,Rev};
 use ops;
-use util::{CargoResult, Wrap, Require, simple_human, other_error};
+use ops::{Lockfile,load_lockfile,write_lockfile};
+use ops::workspace::{Workspace,dependencies_of};
+use util::{CargoResult, CargoError, Wrap, Require, simple_human, human_error, other_error};
+use util::result::HumanReadableError;
+
+pub struct CompileOptions {
+    // The number of rustc processes to run at once
+    pub jobs: uint,
+    // The name of the profile to compile with
+    pub profile: String,
+    // The triple to cross-compile for, if not the host
+    pub target: Option<String>,
+    // The features of the package to enable, and whether to enable its
+    // `default` feature as well
+    pub features: Vec<String>,
+    pub default_features: bool,
+    // Whether to leave out the `Compiling`/`Fresh` lines, e.g. when stdout
+    // is read by another program
+    pub quiet: bool,
+    // Whether to compile every member of the package's workspace
+    pub all: bool
+}
+
+impl CompileOptions {
+    pub fn new() -> CompileOptions {
+        CompileOptions {
+            jobs: os::num_cpus(),
+            profile: "dev".to_str(),
+            target: None,
+            features: Vec::new(),
+            default_features: true,
+            quiet: false,
+            all: false
+        }
+    }
 
-pub fn compile(manifest_path: &Path) -> CargoResult<()> {
+    /**
+     * The options given by the flags that every command compiling a package
+     * shares. Features are separated by spaces, e.g. `--features "tls gzip"`.
+     */
+    pub fn from_flags(jobs: Option<uint>, release: bool, features: Option<&str>, no_default_features: bool) -> CargoResult<CompileOptions> {
+        let mut options = CompileOptions::new();
+
+        match jobs {
+            Some(0) => return Err(simple_human("The number of jobs must be at least 1")),
+            Some(jobs) => options.jobs = jobs,
+            None => ()
+        }
+
+        if release {
+            options.profile = "release".to_str();
+        }
+
+        options.features = features.map(|features| features.words().map(|f| f.to_str()).collect()).unwrap_or_else(|| Vec::new());
+        options.default_features = !no_default_features;
+
+        Ok(options)
+    }
+}
+
+pub fn compile(manifest_path: &Path, options: &CompileOptions) -> CargoResult<()> {
     log!(4, "compile; manifest-path={}", manifest_path.display());
 
+    match try!(Workspace::find(manifest_path)) {
+        Some(ws) => return compile_workspace(&ws, manifest_path, options),
+        None => ()
+    }
+
     // TODO: Move this into PathSource
     let package = try!(PathSource::read_package(manifest_path));
     debug!("loaded package; package={}", package);
 
-    let sources = try!(sources_for(&package));
+    let (packages, resolve) = try!(resolve_and_fetch(&package, manifest_path, false, options));
+
+    let package_set = PackageSet::new(packages.as_slice());
+
+    try!(ops::compile_packages(&package, &package_set, &resolve, options));
+
+    Ok(())
+}
+
+/**
+ * Compiles members of a workspace into its shared target directory: every
+ * member with `--all` or when run from a root manifest that only lists the
+ * members, otherwise the package at `manifest_path`. The whole workspace is
+ * resolved at once, with the Cargo.lock at its root, so all members use the
+ * same versions of their dependencies.
+ */
+fn compile_workspace(ws: &Workspace, manifest_path: &Path, options: &CompileOptions) -> CargoResult<()> {
+    let dir = manifest_path.dir_path();
+    let all = options.all || (ws.is_virtual() && dir == *ws.get_root());
+
+    let selected: Vec<String> = ws.get_members().iter()
+        .filter(|member| all || member.get_root() == dir)
+        .map(|member| member.get_name().to_str())
+        .collect();
+
+    debug!("compiling workspace; root={}; members={}", ws.get_root().display(), selected);
+
+    let root = try!(ws.to_package(selected.as_slice(), options.features.as_slice(), options.default_features));
+    let (packages, resolve) = try!(resolve_and_fetch(&root, &ws.get_manifest_path(), false, options));
+    let target_dir = ws.get_root().join("target");
+
+    for name in selected.iter() {
+        let mut member = try!(packages.iter().find(|pkg| pkg.get_name() == name.as_slice()).require(
+            simple_human(format!("Could not resolve the workspace member `{}`", name)))).clone();
+        member.set_target_dir(&target_dir);
+
+        let deps = dependencies_of(&member, packages.as_slice(), false);
+        try!(ops::compile_packages(&member, &PackageSet::new(deps.as_slice()), &resolve, options));
+    }
+
+    Ok(())
+}
+
+/**
+ * Resolves the dependencies of `package`, with the features named by the
+ * options enabled, and fetches them from their sources. Development
+ * dependencies are only included when `include_dev` is set, i.e. when the
+ * package's tests are being built.
+ *
+ * If a `Cargo.lock` exists and was written for the package's current
+ * requirements, the dependencies are resolved within its locked versions
+ * and git revisions. The lockfile records the resolve with the default
+ * features and without development dependencies, and is (re)written
+ * whenever that resolve differs from it.
+ */
+pub fn resolve_and_fetch(package: &Package, manifest_path: &Path, include_dev: bool,
+                         options: &CompileOptions) -> CargoResult<(Vec<Package>, Resolve)> {
+    let existing = try!(load_lockfile(manifest_path));
+    let lock = existing.clone().filtered(|lock| lock.is_fresh_for(package));
+
+    let git_refs = git_references(package, include_dev, lock.as_ref());
+    let sources = try!(sources_for(package, include_dev, git_refs.as_slice()));
 
     try!(sources.update().wrap("unable to update sources"));
     let summaries = try!(sources.list().wrap("unable to list packages from source"));
-    let resolved = try!(resolve(package.get_dependencies(), &summaries).wrap("unable to resolve dependencies"));
 
-    try!(sources.download(resolved.as_slice()).wrap("unable to download packages"));
+    let features = try!(package.get_summary().expand_features(options.features.as_slice(), options.default_features));
+    let deps = dependencies_to_resolve(package, include_dev, features.as_slice());
 
-    let packages = try!(sources.get(resolved.as_slice()).wrap("unable to get packages from source"));
+    // The locked versions can only be kept while they are all available
+    let locked = lock.as_ref().filtered(|lock| lock.locked_ids(summaries.as_slice()).is_some());
 
-    log!(5, "fetch packages from source; packages={}; ids={}", packages, resolved);
+    let permitted: Vec<Summary> = summaries.iter()
+        .filter(|summary| locked.map(|lock| lock.permits(summary.get_package_id())).unwrap_or(true))
+        .map(|summary| summary.clone())
+        .collect();
 
-    let package_set = PackageSet::new(packages.as_slice());
+    let mut resolve = match resolve_features(deps.as_slice(), &permitted) {
+        Ok(resolve) => resolve,
+        // The resolver's own errors already name the dependency it could
+        // not resolve
+        Err(err @ CargoError { kind: HumanReadableError, .. }) => return Err(err),
+        Err(err) => return Err(err).wrap("unable to resolve dependencies")
+    };
+    resolve.set_features(package.get_name(), features);
 
-    try!(ops::compile_packages(&package, &package_set));
+    try!(sources.download(resolve.get_package_ids()).wrap("unable to download packages"));
 
-    Ok(())
+    let packages = try!(sources.get(resolve.get_package_ids()).wrap("unable to get packages from source"));
+
+    log!(5, "fetch packages from source; packages={}; resolve={}", packages, resolve);
+
+    let revisions = try!(git_revisions(git_refs.as_slice()));
+    let new_lock = Lockfile::new(package, packages.as_slice(), revisions.as_slice());
+    let default_resolve = !include_dev && options.features.is_empty() && options.default_features;
+
+    if default_resolve && existing.as_ref() != Some(&new_lock) {
+        try!(write_lockfile(manifest_path, &new_lock).wrap("unable to write Cargo.lock"));
+    }
+
+    Ok((packages, resolve))
 }
 
-fn sources_for(package: &Package) -> CargoResult<SourceSet> {
-    let mut sources = try!(sources_from_config([package.get_manifest_path().dir_path()]));
+/**
+ * The root package's dependencies, optionally including its development
+ * dependencies. Optional dependencies are only included when one of the
+ * enabled `features` names them.
+ */
+pub fn dependencies_to_resolve(package: &Package, include_dev: bool, features: &[String]) -> Vec<Dependency> {
+    package.get_dependencies().iter()
+        .filter(|dep| include_dev || dep.is_transitive())
+        .filter(|dep| dep.is_active(features))
+        .map(|dep| dep.clone())
+        .collect()
+}
 
-    let git_sources: Vec<Box<Source>> = try!(result::collect(package.get_sources().iter().map(|source_id: &SourceId| {
-        match source_id.kind {
-            GitKind(ref reference) => {
-                let remote = GitRemote::new(source_id.url.clone(), false);
-                let home = try!(os::homedir().require(simple_human("Cargo couldn't find a home directory")));
-                let git = home.join(".cargo").join("git");
-                let ident = url_to_path_ident(&source_id.url);
-
-                // .cargo/git/db
-                // .cargo/git/checkouts
-                let db_path = git.join("db").join(ident.as_slice());
-                let checkout_path = git.join("checkouts").join(ident.as_slice()).join(reference.as_slice());
-                Ok(box GitSource::new(remote, reference.clone(), db_path, checkout_path) as Box<Source>)
-            }
-        }
+/**
+ * The sources of the package's dependencies, including those of its
+ * development dependencies if `include_dev` is set.
+ */
+fn sources_of(package: &Package, include_dev: bool) -> Vec<SourceId> {
+    let mut sources = Vec::from_slice(package.get_sources());
+
+    if include_dev {
+        sources.push_all(package.get_dev_sources());
+    }
+
+    sources
+}
+
+/**
+ * Pairs each git source of the package with the reference it should be
+ * checked out at: the revision recorded in the lockfile if there is one,
+ * otherwise the branch, tag or revision from the manifest.
+ */
+pub fn git_references(package: &Package, include_dev: bool, lock: Option<&Lockfile>) -> Vec<(SourceId, GitReference)> {
+    sources_of(package, include_dev).iter().filter_map(|source_id| {
+        let reference = match source_id.kind {
+            GitKind(ref reference) => reference,
+            PathKind => return None
+        };
+
+        let locked = lock.and_then(|lock| lock.revision_for(source_id.url.to_str().as_slice()));
+
+        Some((source_id.clone(), locked.map(|rev| Rev(rev.to_str())).unwrap_or_else(|| reference.clone())))
+    }).collect()
+}
+
+pub fn sources_for(package: &Package, include_dev: bool, git_refs: &[(SourceId, GitReference)]) -> CargoResult<SourceSet> {
+    let mut paths = vec!(package.get_manifest_path().dir_path());
+    try!(path_dependency_dirs(package, include_dev, &mut paths));
+
+    let mut sources = try!(sources_from_config(paths.as_slice()));
+
+    let git_sources: Vec<Box<Source>> = try!(result::collect(git_refs.iter().map(|&(ref source_id, ref reference)| {
+        let (db_path, checkouts_path) = try!(git_paths(&source_id.url));
+        let remote = GitRemote::new(source_id.url.clone(), false);
+        Ok(box GitSource::new(remote, reference.clone(), db_path, checkouts_path) as Box<Source>)
     })));
 
     sources.push_all_move(git_sources);
@@ -82,6 +269,61 @@ fn sources_for(package: &Package) -> CargoResult<SourceSet> {
     Ok(SourceSet::new(sources))
 }
 
+/**
+ * Returns the exact revision each git source was checked out at, keyed by
+ * the source's URL. The sources must already have been updated.
+ */
+pub fn git_revisions(git_refs: &[(SourceId, GitReference)]) -> CargoResult<Vec<(String, String)>> {
+    result::collect(git_refs.iter().map(|&(ref source_id, ref reference)| {
+        let (db_path, _) = try!(git_paths(&source_id.url));
+        let db = GitRemote::new(source_id.url.clone(), false).db_at(&db_path);
+        let rev = try!(db.rev_for(reference.as_slice()));
+
+        Ok((source_id.url.to_str(), rev))
+    }))
+}
+
+/**
+ * Collects the directories of the package's path dependencies, and of their
+ * path dependencies in turn, into `dst`. Development dependencies are only
+ * followed for the package itself, and only if `include_dev` is set.
+ */
+fn path_dependency_dirs(package: &Package, include_dev: bool, dst: &mut Vec<Path>) -> CargoResult<()> {
+    for source_id in sources_of(package, include_dev).iter() {
+        match source_id.kind {
+            PathKind => (),
+            GitKind(..) => continue
+        }
+
+        let dir = Path::new(source_id.url.path.as_slice());
+
+        if dst.contains(&dir) {
+            continue;
+        }
+
+        let dep = try!(PathSource::read_package(&dir.join("Cargo.toml")).map_err(|err|
+            human_error(format!("Could not read the path dependency at `{}`", dir.display()), format!("package={}", package), err)));
+
+        dst.push(dir);
+        try!(path_dependency_dirs(&dep, false, dst));
+    }
+
+    Ok(())
+}
+
+fn git_paths(url: &Url) -> CargoResult<(Path, Path)> {
+    let home = try!(os::homedir().require(simple_human("Cargo couldn't find a home directory")));
+    let git = home.join(".cargo").join("git");
+    let ident = url_to_path_ident(url);
+
+    // .cargo/git/db
+    // .cargo/git/checkouts
+    let db_path = git.join("db").join(ident.as_slice());
+    let checkouts_path = git.join("checkouts").join(ident.as_slice());
+
+    Ok((db_path, checkouts_path))
+}
+
 fn sources_from_config(additional: &[Path]) -> CargoResult<Vec<Box<Source>>> {
     let configs = try!(config::all_configs(os::getcwd()));
 
@@ -96,7 +338,53 @@ fn sources_from_config(additional: &[Path]) -> CargoResult<Vec<Box<Source>>> {
 
     paths.push_all(additional);
 
-    Ok(vec!(box PathSource::new(paths) as Box<Source>))
+    // Directories to search for packages are opt-in: `recursive-paths`
+    // lists them, `paths-ignore` the directories below them to leave out and
+    // `paths-depth` how deep to search
+    let roots = try!(config_list(&configs, "recursive-paths"));
+    let ignored = try!(config_list(&configs, "paths-ignore"));
+
+    let max_depth = match configs.find_equiv(&"paths-depth").map(|v| v.get_value()) {
+        Some(&config::String(ref depth)) => try!(from_str::<uint>(depth.as_slice()).require(
+            simple_human(format!("`paths-depth` must be a number of directories, but it was `{}`", depth)))),
+        Some(&config::List(_)) => return Err(simple_human("`paths-depth` was configured as a List instead of a String")),
+        None => path::DEFAULT_DEPTH
+    };
+
+    let source = PathSource::new(paths)
+        .recursive(roots.iter().map(|root| Path::new(root.as_slice())).collect(), ignored, max_depth);
+
+    let mut sources = vec!(box source as Box<Source>);
+
+    match configs.find_equiv(&"registry").map(|v| v.get_value()) {
+        Some(&config::String(ref url)) => sources.push(box try!(registry_source(url.as_slice())) as Box<Source>),
+        Some(&config::List(_)) => return Err(simple_human("The registry was configured as a List instead of a String")),
+        None => ()
+    }
+
+    Ok(sources)
+}
+
+fn config_list(configs: &HashMap<String, ConfigValue>, key: &str) -> CargoResult<Vec<String>> {
+    match configs.find_equiv(&key).map(|v| v.get_value()) {
+        Some(&config::List(ref list)) => Ok(list.clone()),
+        Some(&config::String(_)) => Err(simple_human(format!("`{}` was configured as a String instead of a List", key))),
+        None => Ok(Vec::new())
+    }
+}
+
+/**
+ * The registry configured with `registry = "<url>"` is synced into
+ * `~/.cargo/registry/<ident>`, keyed by its URL like git databases are.
+ */
+fn registry_source(url: &str) -> CargoResult<RegistrySource> {
+    let url = try!(url::from_str(url).map_err(|_|
+        simple_human(format!("The configured registry `{}` is not a valid URL", url))));
+
+    let home = try!(os::homedir().require(simple_human("Cargo couldn't find a home directory")));
+    let root = home.join(".cargo").join("registry").join(url_to_path_ident(&url).as_slice());
+
+    Ok(RegistrySource::new(url, root))
 }
 
 fn url_to_path_ident(url: &Url) -> String {
@@ -0,0 +1,127 @@
+/**
+ * Cargo new creates a project that builds as soon as it exists:
+ *
+ * 1. Check that the directory does not exist yet and that its name is a
+ *    valid crate name
+ * 2. Write a manifest with a single lib target, or a bin target with
+ *    `--bin`, and a source file for it
+ * 3. Write a `.gitignore` for the target directory and optionally run
+ *    `git init`
+ */
+
+use std::os;
+use std::io::{fs,File,UserDir};
+use std::str;
+use util::{CargoResult,Require,io_error,simple_human,process};
+
+pub struct NewOptions<'a> {
+    // Where to create the project; its last component is the project name
+    pub path: &'a str,
+    // Whether the project is a program rather than a library
+    pub bin: bool,
+    // Whether to run `git init` in the new directory
+    pub git: bool
+}
+
+pub fn new(opts: NewOptions) -> CargoResult<Path> {
+    log!(4, "new; path={}; bin={}; git={}", opts.path, opts.bin, opts.git);
+
+    let path = os::getcwd().join(opts.path);
+
+    if path.exists() {
+        return Err(simple_human(format!("Destination `{}` already exists", path.display())));
+    }
+
+    let name = try!(path.filename_str().require(simple_human(format!("Cannot create a project at `{}`", opts.path)))).to_str();
+    try!(validate_name(name.as_slice()));
+
+    try!(fs::mkdir_recursive(&path.join("src"), UserDir).map_err(io_error));
+
+    let (src, target) = if opts.bin {
+        ("src/main.rs".to_str(), format!("[[bin]]\n\nname = \"{}\"\npath = \"src/main.rs\"\n", name))
+    } else {
+        (format!("src/{}.rs", name), format!("[[lib]]\n\nname = \"{}\"\n", name))
+    };
+
+    let manifest = format!("[project]\n\nname = \"{}\"\nversion = \"0.1.0\"\nauthors = [{}]\n\n{}",
+                           name, author().map(|author| toml_string(author.as_slice())).unwrap_or(String::new()), target);
+
+    try!(write(&path.join("Cargo.toml"), manifest.as_slice()));
+    try!(write(&path.join(src.as_slice()), if opts.bin { MAIN } else { LIB }));
+    try!(write(&path.join(".gitignore"), "/target\n"));
+
+    if opts.git {
+        try!(process("git").args(["init"]).cwd(path.clone()).exec_with_output());
+    }
+
+    Ok(path)
+}
+
+static MAIN: &'static str = "fn main() {
+    println!(\"Hello, world!\")
+}
+";
+
+static LIB: &'static str = "#[test]
+fn it_works() {
+}
+";
+
+/**
+ * A crate name starts with a letter or an underscore, followed by letters,
+ * digits and underscores.
+ */
+fn validate_name(name: &str) -> CargoResult<()> {
+    let valid = name.chars().enumerate().all(|(i, c)| {
+        c == '_' || c.is_alphabetic() || (i > 0 && c.is_digit())
+    });
+
+    if name.is_empty() || !valid {
+        return Err(simple_human(format!("`{}` is not a valid crate name; use letters, digits and underscores, starting with a letter or an underscore", name)));
+    }
+
+    Ok(())
+}
+
+/**
+ * The author of a new project: the name and email from git's config,
+ * falling back to the `USER` and `EMAIL` environment variables.
+ */
+fn author() -> Option<String> {
+    let name = git_config("user.name").or_else(|| os::getenv("USER"));
+    let email = git_config("user.email").or_else(|| os::getenv("EMAIL"));
+
+    match (name, email) {
+        (Some(name), Some(email)) => Some(format!("{} <{}>", name, email)),
+        (Some(name), None) => Some(name),
+        (None, _) => None
+    }
+}
+
+fn git_config(key: &str) -> Option<String> {
+    process("git").args(["config", key]).exec_with_output().ok()
+        .map(|output| str::from_utf8_lossy(output.output.as_slice()).as_slice().trim().to_str())
+        .filtered(|value| !value.is_empty())
+}
+
+fn toml_string(s: &str) -> String {
+    format!("\"{}\"", s.replace("\\", "\\\\").replace("\"", "\\\""))
+}
+
+fn write(path: &Path, contents: &str) -> CargoResult<()> {
+    File::create(path).write_str(contents).map_err(io_error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::validate_name;
+
+    #[test]
+    pub fn test_validate_name() {
+        assert!(validate_name("foo").is_ok());
+        assert!(validate_name("_foo_2").is_ok());
+        assert!(validate_name("2foo").is_err());
+        assert!(validate_name("foo-bar").is_err());
+        assert!(validate_name("").is_err());
+    }
+}
@@ -0,0 +1,194 @@
+/**
+ * Cargo package turns a package into a distributable source archive:
+ *
+ * 1. Read the manifest and check that it has the metadata a registry needs
+ * 2. Collect the files belonging to the package: its manifest, README,
+ *    build script and target sources, skipping the target directory and
+ *    VCS metadata
+ * 3. Copy them into `target/package/<name>-<version>` and archive that
+ *    directory as `target/package/<name>-<version>.crate`
+ * 4. Unpack the archive into a temporary directory and compile it, to make
+ *    sure that nothing the build needs was left out
+ */
+
+use std::io;
+use std::io::{fs,UserDir,TempDir};
+use core::Package;
+use sources::PathSource;
+use ops;
+use util::{CargoResult,Require,io_error,human_error,simple_human,process};
+
+// Directories that never belong in a package
+static IGNORED: &'static [&'static str] = &[".git", ".hg", ".svn"];
+
+pub fn package(manifest_path: &Path, verify: bool) -> CargoResult<Path> {
+    log!(4, "package; manifest-path={}", manifest_path.display());
+
+    let pkg = try!(PathSource::read_package(manifest_path));
+    try!(check_metadata(&pkg));
+
+    println!("Packaging {}", pkg);
+
+    let archive = try!(build_archive(&pkg));
+
+    if verify {
+        println!("Verifying {}", pkg);
+        try!(verify_archive(&pkg, &archive));
+    }
+
+    Ok(archive)
+}
+
+/**
+ * The file name of the archive for a package, e.g. `foo-0.5.0.crate`.
+ */
+pub fn archive_name(pkg: &Package) -> String {
+    format!("{}.crate", package_dir_name(pkg))
+}
+
+fn package_dir_name(pkg: &Package) -> String {
+    format!("{}-{}", pkg.get_name(), pkg.get_version())
+}
+
+fn check_metadata(pkg: &Package) -> CargoResult<()> {
+    if pkg.get_manifest().get_authors().is_empty() {
+        return Err(simple_human(format!("Package `{}` has no authors; at least one author is required to package it", pkg.get_name())));
+    }
+
+    Ok(())
+}
+
+/**
+ * Returns the paths, relative to the package root, of every file that
+ * belongs in the package: the manifest, any README, the build script and
+ * the directories holding the sources of its targets and its integration
+ * tests. Symlinked directories and directories holding another package
+ * are not followed.
+ */
+pub fn list_files(pkg: &Package) -> CargoResult<Vec<Path>> {
+    let root = pkg.get_root();
+    let target_dir = pkg.get_absolute_target_dir();
+    let mut ret = vec!(Path::new("Cargo.toml"));
+
+    let entries = try!(readdir(&root));
+
+    for entry in entries.iter() {
+        let is_readme = entry.filename_str().map(|name| name.starts_with("README")).unwrap_or(false);
+
+        if is_readme && entry.is_file() {
+            ret.push(try!(relative(&root, entry)));
+        }
+    }
+
+    match pkg.get_manifest().get_build() {
+        Some(build) if build.ends_with(".rs") && root.join(build).is_file() => ret.push(Path::new(build)),
+        _ => ()
+    }
+
+    // A target in the package root brings just its own file along, rather
+    // than everything in the root
+    let mut dirs = vec!(root.join("tests"));
+
+    for target in pkg.get_targets().iter() {
+        let path = root.join(target.get_path());
+        let dir = path.dir_path();
+
+        if dir == root {
+            if path.is_file() {
+                ret.push(try!(relative(&root, &path)));
+            }
+        } else if root.is_ancestor_of(&dir) && !dirs.contains(&dir) {
+            dirs.push(dir);
+        }
+    }
+
+    for dir in dirs.iter().filter(|dir| dir.is_dir()) {
+        try!(walk(&root, dir, &target_dir, &mut ret));
+    }
+
+    ret.sort();
+    ret.dedup();
+
+    Ok(ret)
+}
+
+fn walk(root: &Path, dir: &Path, target_dir: &Path, dst: &mut Vec<Path>) -> CargoResult<()> {
+    for entry in try!(readdir(dir)).iter() {
+        if entry == target_dir {
+            continue;
+        }
+
+        let stat = try!(fs::lstat(entry).map_err(|err|
+            human_error(format!("Couldn't read `{}`", entry.display()), None::<&str>, io_error(err))));
+
+        match stat.kind {
+            io::TypeDirectory => {
+                let ignored = entry.filename_str().map(|name| IGNORED.contains(&name)).unwrap_or(false);
+
+                // A directory with a manifest of its own is another package,
+                // e.g. a path dependency kept inside this one
+                if !ignored && !entry.join("Cargo.toml").exists() {
+                    try!(walk(root, entry, target_dir, dst));
+                }
+            },
+            // A symlink is packaged as the file it points to, if any
+            io::TypeSymlink if !entry.is_file() => (),
+            _ => dst.push(try!(relative(root, entry)))
+        }
+    }
+
+    Ok(())
+}
+
+fn readdir(dir: &Path) -> CargoResult<Vec<Path>> {
+    fs::readdir(dir).map_err(|err|
+        human_error(format!("Couldn't read `{}`", dir.display()), None::<&str>, io_error(err)))
+}
+
+fn relative(root: &Path, path: &Path) -> CargoResult<Path> {
+    path.path_relative_from(root).require(
+        simple_human(format!("`{}` is not inside the package", path.display())))
+}
+
+fn build_archive(pkg: &Package) -> CargoResult<Path> {
+    let out_dir = pkg.get_absolute_target_dir().join("package");
+    let dir_name = package_dir_name(pkg);
+    let stage = out_dir.join(dir_name.as_slice());
+    let archive = out_dir.join(archive_name(pkg));
+
+    if stage.exists() {
+        try!(fs::rmdir_recursive(&stage).map_err(io_error));
+    }
+
+    let root = pkg.get_root();
+
+    for file in try!(list_files(pkg)).iter() {
+        let dest = stage.join(file);
+
+        try!(fs::mkdir_recursive(&dest.dir_path(), UserDir).map_err(io_error));
+        try!(fs::copy(&root.join(file), &dest).map_err(|err|
+            human_error(format!("Couldn't copy `{}` into the package", file.display()), None::<&str>, io_error(err))));
+    }
+
+    try!(process("tar")
+         .args(["-czf".to_str(), archive.display().to_str(), "-C".to_str(), out_dir.display().to_str(), dir_name])
+         .exec_with_output()
+         .map_err(|err| human_error(format!("Couldn't create `{}`", archive.display()), None::<&str>, err)));
+
+    Ok(archive)
+}
+
+fn verify_archive(pkg: &Package, archive: &Path) -> CargoResult<()> {
+    let tmp = try!(TempDir::new("cargo-package").require(
+        simple_human("Couldn't create a temporary directory to verify the package in")));
+
+    try!(process("tar")
+         .args(["-xzf".to_str(), archive.display().to_str(), "-C".to_str(), tmp.path().display().to_str()])
+         .exec_with_output()
+         .map_err(|err| human_error(format!("Couldn't unpack `{}`", archive.display()), None::<&str>, err)));
+
+    let manifest_path = tmp.path().join(package_dir_name(pkg)).join("Cargo.toml");
+
+    ops::compile(&manifest_path, &ops::CompileOptions::new()).map_err(|err|
+        human_error(format!("Failed to verify the package; `{}` does not compile on its own", archive_name(pkg)), None::<&str>, err))
+}
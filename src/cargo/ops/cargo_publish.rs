@@ -0,0 +1,129 @@
+/**
+ * Cargo publish uploads a package to the configured registry:
+ *
+ * 1. Package (and, unless asked not to, verify) the package with `cargo package`
+ * 2. Write the package's metadata, as JSON, next to the archive
+ * 3. PUT the metadata and the archive to `<registry>/api/v1/packages/new`,
+ *    authenticating with the `token` from `.cargo/config`
+ *
+ * The registry answers with a 2xx status once the package is published, 401
+ * or 403 if the token was refused and 409 if the version was already
+ * published.
+ */
+
+use std::io;
+use std::io::{fs,File};
+use std::os;
+use std::str;
+use std::collections::HashMap;
+use serialize::json;
+use core::Package;
+use core::dependency::SerializedDependency;
+use sources::PathSource;
+use ops;
+use util::config;
+use util::{CargoResult,Require,io_error,human_error,simple_human,process};
+
+pub fn publish(manifest_path: &Path, verify: bool) -> CargoResult<()> {
+    log!(4, "publish; manifest-path={}", manifest_path.display());
+
+    let registry = try!(try!(config::get_string(os::getcwd(), "registry")).require(
+        simple_human("No registry is configured; add `registry = \"<url>\"` to .cargo/config")));
+
+    let token = try!(try!(config::get_string(os::getcwd(), "token")).require(
+        simple_human("No token is configured for the registry; add `token = \"<token>\"` to .cargo/config")));
+
+    if !registry.as_slice().starts_with("http://") && !registry.as_slice().starts_with("https://") {
+        return Err(simple_human(format!("Cannot publish to `{}`; only HTTP registries accept uploads", registry)));
+    }
+
+    let pkg = try!(PathSource::read_package(manifest_path));
+    let archive = try!(ops::package(manifest_path, verify));
+    let metadata = try!(write_metadata(&pkg, &archive));
+
+    println!("Uploading {} v{} to `{}`", pkg.get_name(), pkg.get_version(), registry);
+
+    upload(&pkg, registry.as_slice(), token.as_slice(), &metadata, &archive)
+}
+
+/**
+ * The metadata document the registry indexes the package by: its name,
+ * version, authors, features and dependencies with their version
+ * requirements. Nothing that only makes sense on this machine, such as the
+ * target directory, is sent.
+ */
+#[deriving(Encodable)]
+struct Metadata {
+    name: String,
+    version: String,
+    dependencies: Vec<SerializedDependency>,
+    features: HashMap<String, Vec<String>>,
+    authors: Vec<String>
+}
+
+fn write_metadata(pkg: &Package, archive: &Path) -> CargoResult<Path> {
+    let path = archive.with_extension("json");
+    let summary = pkg.get_summary();
+
+    let metadata = Metadata {
+        name: pkg.get_name().to_str(),
+        version: pkg.get_version().to_str(),
+        dependencies: summary.get_dependencies().iter().map(|dep| SerializedDependency::from_dependency(dep)).collect(),
+        features: summary.get_features().clone(),
+        authors: Vec::from_slice(pkg.get_manifest().get_authors())
+    };
+
+    let json = json::Encoder::str_encode(&metadata);
+
+    let mut file = try!(File::create(&path).map_err(io_error));
+    try!(file.write_str(json.as_slice()).map_err(io_error));
+
+    Ok(path)
+}
+
+/**
+ * Writes the token to a curl config file only the current user can read,
+ * so that it never shows up in curl's arguments.
+ */
+fn write_auth_config(token: &str, archive: &Path) -> CargoResult<Path> {
+    let path = archive.with_extension("auth");
+    let header = format!("Authorization: {}", token);
+
+    let mut file = try!(File::create(&path).map_err(io_error));
+    try!(fs::chmod(&path, io::UserRead | io::UserWrite).map_err(io_error));
+    try!(file.write_str(format!("header = \"{}\"\n", header.replace("\\", "\\\\").replace("\"", "\\\"")).as_slice()).map_err(io_error));
+
+    Ok(path)
+}
+
+fn upload(pkg: &Package, registry: &str, token: &str, metadata: &Path, archive: &Path) -> CargoResult<()> {
+    let url = format!("{}/api/v1/packages/new", registry.trim_right_chars('/'));
+    let response = archive.with_extension("response");
+    let auth = try!(write_auth_config(token, archive));
+
+    let output = process("curl")
+        .args(["-sS".to_str(), "-X".to_str(), "PUT".to_str(),
+               "-H".to_str(), "Expect:".to_str(),
+               "-K".to_str(), auth.display().to_str(),
+               "-F".to_str(), format!("metadata=@{}", metadata.display()),
+               "-F".to_str(), format!("package=@{}", archive.display()),
+               "-o".to_str(), response.display().to_str(),
+               "-w".to_str(), "%{http_code}".to_str(),
+               url.clone()])
+        .exec_with_output();
+
+    let _ = fs::unlink(&auth);
+
+    let output = try!(output.map_err(|err|
+        human_error(format!("Couldn't upload {} v{} to `{}`", pkg.get_name(), pkg.get_version(), registry), format!("url={}", url), err)));
+
+    let status = str::from_utf8_lossy(output.output.as_slice()).to_str();
+    let body = File::open(&response).read_to_str().unwrap_or_else(|_| String::new());
+
+    match status.as_slice().trim() {
+        code if code.len() == 3 && code.starts_with("2") => Ok(()),
+        "401" | "403" => Err(simple_human(format!("The registry at `{}` refused the token; check the `token` in .cargo/config", registry))),
+        "409" => Err(simple_human(format!("{} v{} has already been published to `{}`", pkg.get_name(), pkg.get_version(), registry))),
+        code => Err(simple_human(format!("The registry at `{}` failed to publish {} v{} (HTTP {}): {}", registry, pkg.get_name(), pkg.get_version(), code, body.as_slice().trim())))
+    }
+}
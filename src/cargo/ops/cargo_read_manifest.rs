@@ -1,18 +1,135 @@
-use std::io::File;
+use std::io;
+use std::io::{fs,File};
 use util;
 use url::Url;
 use core::{Package,Manifest};
-use util::{CargoResult,io_error};
+use util::{CargoResult,human_error,io_error,warning};
+use util::important_paths::{workspace_members,is_package_manifest};
 
-pub fn read_manifest(contents: &[u8], namespace: &Url) -> CargoResult<Manifest> {
-    util::toml::to_manifest(contents, namespace)
+pub fn read_manifest(contents: &[u8], namespace: &Url, root: &Path) -> CargoResult<Manifest> {
+    util::toml::to_manifest(contents, namespace, root)
 }
 
 pub fn read_package(path: &Path, namespace: &Url) -> CargoResult<Package> {
     log!(5, "read_package; path={}; namespace={}", path.display(), namespace);
     let mut file = try!(File::open(path).map_err(io_error));
     let data = try!(file.read_to_end().map_err(io_error));
-    let manifest = try!(read_manifest(data.as_slice(), namespace));
+    let manifest = try!(read_manifest(data.as_slice(), namespace, &path.dir_path()));
 
     Ok(Package::new(manifest, path))
 }
+
+/**
+ * Reads every package whose manifest is in `dir` or below it, skipping
+ * `target` and `.git` directories. A manifest that only defines a workspace
+ * describes no package and is skipped as well. So is a manifest below `dir`
+ * that cannot be read, e.g. a test fixture, with a warning naming it if
+ * `warn` is set; only the manifest in `dir` itself has to be valid.
+ */
+pub fn read_packages(dir: &Path, namespace: &Url, warn: bool) -> CargoResult<Vec<Package>> {
+    log!(5, "read_packages; dir={}; namespace={}", dir.display(), namespace);
+    let manifests = try!(find_manifests(dir, [], None, warn));
+    let top = dir.join("Cargo.toml");
+    let mut ret = Vec::new();
+
+    for manifest in manifests.iter() {
+        if !describes_package(manifest) {
+            continue;
+        }
+
+        match read_package(manifest, namespace) {
+            Ok(pkg) => ret.push(pkg),
+            Err(err) => {
+                if *manifest == top {
+                    return Err(human_error(format!("Could not read the manifest at `{}`", manifest.display()), None::<&str>, err));
+                }
+
+                if !warn {
+                    continue;
+                }
+
+                let relative = manifest.path_relative_from(dir).unwrap_or_else(|| manifest.clone());
+                warning(format!("skipping the manifest at `{}`: {}", relative.display(), err.get_desc()));
+            }
+        }
+    }
+
+    Ok(ret)
+}
+
+/**
+ * A manifest describes a package unless it only lists the members of a
+ * workspace. One that cannot be parsed is left for reading it to report.
+ */
+fn describes_package(manifest: &Path) -> bool {
+    match workspace_members(manifest) {
+        Ok(Some(_)) => is_package_manifest(manifest).unwrap_or(true),
+        _ => true
+    }
+}
+
+/**
+ * Finds the manifests in `root` and the directories below it, at most
+ * `max_depth` levels down if given. `target` and `.git` directories are
+ * never searched, nor are directories whose name or path relative to `root`
+ * is in `ignored`. Only `root` itself has to be readable; a directory below
+ * it that is not is skipped, with a warning naming it if `warn` is set.
+ */
+pub fn find_manifests(root: &Path, ignored: &[String], max_depth: Option<uint>, warn: bool) -> CargoResult<Vec<Path>> {
+    let mut ret = Vec::new();
+    try!(walk(root, root, 0, ignored, max_depth, warn, &mut ret));
+    Ok(ret)
+}
+
+static IGNORED: &'static [&'static str] = &["target", ".git"];
+
+fn walk(root: &Path, dir: &Path, depth: uint, ignored: &[String], max_depth: Option<uint>, warn: bool, dst: &mut Vec<Path>) -> CargoResult<()> {
+    let mut entries = match fs::readdir(dir) {
+        Ok(entries) => entries,
+        Err(err) => {
+            if dir == root {
+                return Err(human_error(format!("Couldn't read `{}`", dir.display()), None::<&str>, io_error(err)));
+            }
+
+            if warn {
+                warning(format!("skipping `{}`: {}", dir.display(), err.desc));
+            }
+
+            return Ok(());
+        }
+    };
+    entries.sort();
+
+    for entry in entries.iter() {
+        // Symlinked directories are not followed, so that a link back up
+        // the tree cannot send the walk round in circles
+        let stat = try!(fs::lstat(entry).map_err(|err|
+            human_error(format!("Couldn't read `{}`", entry.display()), None::<&str>, io_error(err))));
+
+        if stat.kind == io::TypeDirectory {
+            let too_deep = max_depth.map(|max| depth >= max).unwrap_or(false);
+
+            if !too_deep && !is_ignored(root, entry, ignored) {
+                try!(walk(root, entry, depth + 1, ignored, max_depth, warn, dst));
+            }
+        } else if entry.filename_str() == Some("Cargo.toml") {
+            dst.push(entry.clone());
+        }
+    }
+
+    Ok(())
+}
+
+fn is_ignored(root: &Path, dir: &Path, ignored: &[String]) -> bool {
+    let name = dir.filename_str().unwrap_or("");
+
+    if IGNORED.contains(&name) {
+        return true;
+    }
+
+    let relative = dir.path_relative_from(root);
+
+    ignored.iter().any(|rule| {
+        rule.as_slice() == name || relative.as_ref().map(|rel| *rel == Path::new(rule.as_slice())).unwrap_or(false)
+    })
+}
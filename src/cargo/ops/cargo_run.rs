@@ -0,0 +1,58 @@
+/**
+ * Cargo run builds a package and runs one of its programs:
+ *
+ * 1. Pick the bin target to run: the one named with `--bin`, or the
+ *    package's only one
+ * 2. Compile the package as `cargo compile` would
+ * 3. Run the program in the current directory with the given arguments,
+ *    sharing cargo's stdio
+ */
+
+use std::os;
+use std::io::process::ProcessExit;
+use core::{Package,Target};
+use ops;
+use util::{CargoResult,CargoError,simple_human,process};
+use util::result::ProcessError;
+
+/**
+ * Returns how the program exited when it did not succeed.
+ */
+pub fn run(manifest_path: &Path, options: &ops::CompileOptions, bin: Option<&str>,
+           args: &[String]) -> CargoResult<Option<ProcessExit>> {
+    log!(4, "run; manifest-path={}; bin={}; args={}", manifest_path.display(), bin, args);
+
+    let package = try!(ops::read_member(manifest_path));
+    let target = try!(find_bin(&package, bin)).clone();
+
+    try!(ops::compile(manifest_path, options));
+
+    let exe = try!(ops::output_dir(&package, options)).join(target.get_name());
+    let name = exe.path_relative_from(&os::getcwd()).unwrap_or_else(|| exe.clone());
+    println!("Running `{}`", name.display());
+
+    match process(exe.display().to_str().as_slice()).args(args).exec() {
+        Ok(()) => Ok(None),
+        Err(CargoError { kind: ProcessError(exit, _), .. }) => Ok(Some(exit)),
+        Err(err) => Err(err)
+    }
+}
+
+fn find_bin<'a>(package: &'a Package, bin: Option<&str>) -> CargoResult<&'a Target> {
+    let bins: Vec<&Target> = package.get_targets().iter().filter(|target| target.is_bin()).collect();
+    let names: Vec<&str> = bins.iter().map(|target| target.get_name()).collect();
+
+    match bin {
+        Some(bin) => match bins.iter().find(|target| target.get_name() == bin) {
+            Some(target) => Ok(*target),
+            None => Err(simple_human(format!("Package `{}` has no bin target named `{}`; its bin targets are: {}",
+                                             package.get_name(), bin, names.connect(", "))))
+        },
+        None => match bins.len() {
+            0 => Err(simple_human(format!("Package `{}` has no bin target to run", package.get_name()))),
+            1 => Ok(*bins.get(0)),
+            _ => Err(simple_human(format!("Package `{}` has several bin targets ({}); choose one with `--bin`",
+                                          package.get_name(), names.connect(", "))))
+        }
+    }
+}
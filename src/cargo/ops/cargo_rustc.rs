@@ -1,84 +1,675 @@
-use std::os::args;
+use std::os;
 use std::io;
 use std::path::Path;
 use std::str;
-use core::{Package,PackageSet,Target};
+use std::task;
+use std::collections::{HashMap,HashSet};
+use core::{Package,PackageSet,Profile,Target};
+use core::resolver::Resolve;
+use ops::CompileOptions;
+use ops::build_script::{BuildScript,BuildOutput};
+use ops::fingerprint;
+use ops::fingerprint::Fingerprint;
 use util;
-use util::{other_error,human_error,CargoResult,CargoError,ProcessBuilder};
+use util::config;
+use util::{other_error,human_error,simple_human,io_error,CargoResult,CargoError,ProcessBuilder,Require};
 use util::result::ProcessError;
 
 type Args = Vec<String>;
 
-pub fn compile_packages(pkg: &Package, deps: &PackageSet) -> CargoResult<()> {
-    debug!("compile_packages; pkg={}; deps={}", pkg, deps);
+/**
+ * The platform a package is compiled for. Without `--target` everything is
+ * compiled for the host, into the same directories.
+ */
+#[deriving(PartialEq,Clone,Show)]
+enum Kind {
+    KindHost,
+    KindTarget
+}
+
+/**
+ * The directories one platform's output goes into: the primary package's
+ * targets are placed in the root, its dependencies in `deps` and the files
+ * of build scripts in `build`.
+ */
+#[deriving(Clone)]
+struct Layout {
+    root: Path,
+    deps: Path,
+    build: Path,
+    fingerprint: Path
+}
+
+impl Layout {
+    fn new(root: Path) -> Layout {
+        Layout {
+            deps: root.join("deps"),
+            build: root.join("build"),
+            fingerprint: root.join(".fingerprint"),
+            root: root
+        }
+    }
+
+    fn prepare(&self) -> CargoResult<()> {
+        debug!("creating target dir; path={}", self.root.display());
+        try!(mk_target(&self.root));
+        try!(mk_target(&self.deps));
+        mk_target(&self.fingerprint)
+    }
+}
+
+struct Context {
+    profile: Profile,
+    rustc_version: String,
+    host_triple: String,
+    // The triple being cross-compiled for, and the linker and ar configured
+    // for it in .cargo/config
+    triple: Option<String>,
+    linker: Option<String>,
+    ar: Option<String>,
+    host: Layout,
+    target: Layout,
+    // When cross-compiling, the dependencies needed on the host (plugins and
+    // what they depend on) and the ones needed on the target
+    host_deps: HashSet<String>,
+    target_deps: HashSet<String>,
+    // The features enabled on each package
+    resolve: Resolve,
+    // The fingerprints of the packages compiled so far, by name
+    fingerprints: HashMap<String, String>,
+    quiet: bool
+}
+
+impl Context {
+    fn new(pkg: &Package, deps: &PackageSet, resolve: &Resolve, options: &CompileOptions) -> CargoResult<Context> {
+        let profile = try!(find_profile(pkg, options));
+        let triple = options.target.as_ref().map(|triple| triple.as_slice());
+        let rustc_version = try!(fingerprint::rustc_version());
+
+        let host_triple = try!(rustc_version.as_slice().lines()
+            .find(|line| line.starts_with("host: "))
+            .map(|line| line.slice_from(6).trim().to_str())
+            .require(simple_human("Couldn't determine the host triple from `rustc -v`")));
+
+        let mut cx = Context {
+            host: Layout::new(profile_dir(pkg, &profile, None)),
+            target: Layout::new(profile_dir(pkg, &profile, triple)),
+            profile: profile,
+            rustc_version: rustc_version,
+            host_triple: host_triple,
+            triple: options.target.clone(),
+            linker: None,
+            ar: None,
+            host_deps: HashSet::new(),
+            target_deps: HashSet::new(),
+            resolve: resolve.clone(),
+            fingerprints: HashMap::new(),
+            quiet: options.quiet
+        };
+
+        match triple {
+            Some(triple) => {
+                cx.linker = try!(config::get_string(os::getcwd(), format!("target.{}.linker", triple).as_slice()));
+                cx.ar = try!(config::get_string(os::getcwd(), format!("target.{}.ar", triple).as_slice()));
+                find_platforms(pkg, deps, is_plugin(pkg), true, &mut cx.host_deps, &mut cx.target_deps);
+            },
+            None => ()
+        }
+
+        Ok(cx)
+    }
+
+    /**
+     * The triple a package compiled for `kind` runs on.
+     */
+    fn triple<'a>(&'a self, kind: Kind) -> &'a str {
+        match (kind, self.triple.as_ref()) {
+            (KindTarget, Some(triple)) => triple.as_slice(),
+            _ => self.host_triple.as_slice()
+        }
+    }
+
+    fn layout<'a>(&'a self, kind: Kind) -> &'a Layout {
+        match kind {
+            KindHost => &self.host,
+            KindTarget => &self.target
+        }
+    }
+
+    /**
+     * The platforms a package is compiled for. When cross-compiling, a
+     * dependency that is needed both by a plugin and by the rest of the
+     * build is compiled twice.
+     */
+    fn kinds(&self, pkg: &Package, primary: bool) -> Vec<Kind> {
+        if self.triple.is_none() {
+            return vec!(KindTarget);
+        }
+
+        if primary {
+            return vec!(if is_plugin(pkg) { KindHost } else { KindTarget });
+        }
+
+        let mut kinds = Vec::new();
+
+        if self.host_deps.contains_equiv(&pkg.get_name()) {
+            kinds.push(KindHost);
+        }
+
+        if self.target_deps.contains_equiv(&pkg.get_name()) {
+            kinds.push(KindTarget);
+        }
+
+        kinds
+    }
+}
+
+/**
+ * The work of compiling a package for one platform: its build script, if
+ * it has one, and the rustc invocations for its targets that are out of
+ * date, each with the path and value of the fingerprint to record once
+ * they succeeded.
+ */
+struct Stage {
+    build: Option<BuildScript>,
+    units: Vec<(Vec<ProcessBuilder>, Path, String)>
+}
+
+struct Job {
+    pkg: Package,
+    stages: Vec<Stage>,
+    primary: bool
+}
+
+impl Job {
+    fn run(&self) -> CargoResult<()> {
+        for stage in self.stages.iter() {
+            try!(self.run_stage(stage));
+        }
+
+        Ok(())
+    }
+
+    fn run_stage(&self, stage: &Stage) -> CargoResult<()> {
+        let root = self.pkg.get_root();
+
+        // The native libraries the build script built are linked into the
+        // package's targets
+        let native = match stage.build {
+            Some(ref script) => try!(script.run()).rustc_args(),
+            None => Vec::new()
+        };
+
+        for &(ref rustcs, ref path, ref hash) in stage.units.iter() {
+            for rustc in rustcs.iter() {
+                let rustc = rustc.clone().args(Vec::from_slice(rustc.get_args()).append(native.as_slice()).as_slice());
+                log!(5, "root={}; rustc={}; verbose={}", root.display(), rustc, self.primary);
 
-    let target_dir = pkg.get_absolute_target_dir();
-    let deps_target_dir = target_dir.join("deps");
+                try!((if self.primary {
+                    rustc.exec()
+                } else {
+                    rustc.exec_with_output().and(Ok(()))
+                }).map_err(|e| rustc_to_cargo_err(rustc.get_args().as_slice(), &root, e)));
+            }
 
-    // First ensure that the destination directory exists
-    debug!("creating target dir; path={}", target_dir.display());
-    try!(mk_target(&target_dir));
-    try!(mk_target(&deps_target_dir));
+            try!(fingerprint::write(path, hash.as_slice()));
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ * A target compiled by `compile_packages`: the crate types it was compiled
+ * as and the directory rustc wrote them into.
+ */
+#[deriving(PartialEq,Clone,Show,Encodable)]
+pub struct Artifact {
+    package: String,
+    target: String,
+    kind: String,
+    crate_types: Vec<String>,
+    triple: String,
+    out_dir: String
+}
+
+/**
+ * Compiles the package and its dependencies, returning every target built
+ * for them, fresh or not.
+ */
+pub fn compile_packages(pkg: &Package, deps: &PackageSet, resolve: &Resolve, options: &CompileOptions) -> CargoResult<Vec<Artifact>> {
+    let cx = try!(compile(pkg, deps, resolve, options));
+    let mut artifacts = Vec::new();
 
-    // Traverse the dependencies in topological order
-    for dep in try!(topsort(deps)).iter() {
-        println!("Compiling {}", dep);
-        try!(compile_pkg(dep, &deps_target_dir, &deps_target_dir, false));
+    for dep in deps.iter() {
+        artifacts.push_all(artifacts_for(dep, false, &cx).as_slice());
     }
 
-    println!("Compiling {}", pkg);
-    try!(compile_pkg(pkg, &target_dir, &deps_target_dir, true));
+    artifacts.push_all(artifacts_for(pkg, true, &cx).as_slice());
+    Ok(artifacts)
+}
+
+fn artifacts_for(pkg: &Package, primary: bool, cx: &Context) -> Vec<Artifact> {
+    let mut ret = Vec::new();
+
+    for kind in cx.kinds(pkg, primary).move_iter() {
+        let layout = cx.layout(kind);
+        let dest = if primary { &layout.root } else { &layout.deps };
+
+        for target in pkg.get_targets().iter().filter(|target| primary || target.is_lib()) {
+            ret.push(Artifact {
+                package: pkg.to_str(),
+                target: target.get_name().to_str(),
+                kind: (if target.is_lib() { "lib" } else { "bin" }).to_str(),
+                crate_types: target.rustc_crate_types().iter().map(|crate_type| crate_type.to_str()).collect(),
+                triple: cx.triple(kind).to_str(),
+                out_dir: dest.display().to_str()
+            });
+        }
+    }
+
+    ret
+}
+
+fn compile(pkg: &Package, deps: &PackageSet, resolve: &Resolve, options: &CompileOptions) -> CargoResult<Context> {
+    debug!("compile_packages; pkg={}; deps={}; jobs={}; profile={}; target={}",
+           pkg, deps, options.jobs, options.profile, options.target);
+
+    let mut cx = try!(Context::new(pkg, deps, resolve, options));
+
+    // First ensure that the destination directories exist
+    try!(cx.host.prepare());
+    try!(cx.target.prepare());
+
+    try!(compile_deps(deps, options.jobs, &mut cx));
+
+    match try!(prepare_job(pkg, deps, true, &mut cx)) {
+        Some(job) => try!(job.run()),
+        None => ()
+    }
+
+    Ok(cx)
+}
+
+/**
+ * The profile named by the options. Dependencies are compiled with the
+ * primary package's profiles.
+ */
+fn find_profile(pkg: &Package, options: &CompileOptions) -> CargoResult<Profile> {
+    pkg.get_manifest().get_profile(options.profile.as_slice()).map(|profile| profile.clone()).require(
+        simple_human(format!("Unknown profile `{}`", options.profile)))
+}
+
+/**
+ * The directory the options compile the package's own targets into.
+ */
+pub fn output_dir(pkg: &Package, options: &CompileOptions) -> CargoResult<Path> {
+    let profile = try!(find_profile(pkg, options));
+    Ok(profile_dir(pkg, &profile, options.target.as_ref().map(|triple| triple.as_slice())))
+}
+
+/**
+ * The directory a profile compiles into, e.g. `target` or `target/release`,
+ * or `target/<triple>/release` when cross-compiling.
+ */
+fn profile_dir(pkg: &Package, profile: &Profile, triple: Option<&str>) -> Path {
+    let mut dir = pkg.get_absolute_target_dir();
+
+    match triple {
+        Some(triple) => dir.push(triple),
+        None => ()
+    }
+
+    match profile.get_dest() {
+        Some(dest) => dir.join(dest),
+        None => dir
+    }
+}
+
+fn is_plugin(pkg: &Package) -> bool {
+    pkg.get_targets().iter().any(|target| target.is_lib() && target.is_plugin())
+}
+
+/**
+ * Sorts the dependencies of `pkg` by the platform they run on. Plugins run
+ * inside the compiler, so they and everything they depend on are needed on
+ * the host.
+ */
+fn find_platforms(pkg: &Package, deps: &PackageSet, host: bool, primary: bool,
+                  host_deps: &mut HashSet<String>, target_deps: &mut HashSet<String>) {
+    for dep in pkg.get_dependencies().iter().filter(|dep| primary || dep.is_transitive()) {
+        let dep_pkg = match deps.iter().find(|other| other.get_name() == dep.get_name()) {
+            Some(dep_pkg) => dep_pkg,
+            None => continue
+        };
+
+        let host = host || is_plugin(dep_pkg);
+        let name = dep_pkg.get_name().to_str();
+        let added = if host { host_deps.insert(name) } else { target_deps.insert(name) };
+
+        if added {
+            find_platforms(dep_pkg, deps, host, false, host_deps, target_deps);
+        }
+    }
+}
+
+/**
+ * Compiles the dependencies, running up to `jobs` rustc processes at once.
+ * A package is started as soon as all of its own dependencies are done.
+ * Once a package fails to compile no more are started; the ones already
+ * running are waited for, and every failure is reported.
+ */
+fn compile_deps(deps: &PackageSet, jobs: uint, cx: &mut Context) -> CargoResult<()> {
+    // Topological order keeps the output stable when building serially
+    let sorted = try!(topsort(deps));
+    let mut pending: Vec<&Package> = sorted.iter().collect();
+    let mut finished = HashSet::new();
+    let mut failed = Vec::new();
+    let mut running = 0u;
+    let (tx, rx) = channel();
+
+    loop {
+        while failed.is_empty() && running < jobs {
+            let pkg = match pending.iter().position(|pkg| is_ready(*pkg, deps, &finished)) {
+                Some(i) => pending.remove(i).unwrap(),
+                None => break
+            };
+
+            match prepare_job(pkg, deps, false, cx) {
+                Ok(Some(job)) => {
+                    let tx = tx.clone();
+                    let name = pkg.get_name().to_str();
+                    let desc = pkg.to_str();
+                    running += 1;
+
+                    spawn(proc() {
+                        // A job that fails still has to report back, or the
+                        // loop below would wait for it forever
+                        let result = match task::try(proc() job.run()) {
+                            Ok(result) => result,
+                            Err(_) => Err(simple_human("the compiler job failed unexpectedly"))
+                        };
+
+                        let _ = tx.send_opt((name, desc, result));
+                    });
+                },
+                Ok(None) => { finished.insert(pkg.get_name().to_str()); },
+                Err(err) => failed.push((pkg.to_str(), err))
+            }
+        }
+
+        if running == 0 {
+            break;
+        }
+
+        let (name, desc, result) = rx.recv();
+        running -= 1;
+
+        match result {
+            Ok(()) => { finished.insert(name); },
+            Err(err) => failed.push((desc, err))
+        }
+    }
+
+    if !failed.is_empty() {
+        let msgs: Vec<String> = failed.iter()
+            .map(|&(ref pkg, ref err)| format!("Failed to compile {}:\n{}", pkg, err.get_desc()))
+            .collect();
+
+        return Err(simple_human(msgs.connect("\n\n")));
+    }
 
     Ok(())
 }
 
-fn compile_pkg(pkg: &Package, dest: &Path, deps_dir: &Path, primary: bool) -> CargoResult<()> {
-    debug!("compile_pkg; pkg={}; targets={}", pkg, pkg.get_targets());
+/**
+ * Whether every dependency of `pkg` within `deps` has been compiled.
+ */
+fn is_ready(pkg: &Package, deps: &PackageSet, finished: &HashSet<String>) -> bool {
+    pkg.get_dependencies().iter()
+        .filter(|dep| dep.is_transitive())
+        .filter(|dep| deps.iter().any(|other| other.get_name() == dep.get_name()))
+        .all(|dep| finished.contains_equiv(&dep.get_name()))
+}
+
+/**
+ * Compiles the package and its dependencies, then a `--test` harness for
+ * each of the package's targets and for each file in its `tests` directory.
+ * Integration tests are linked against the package's lib. Returns the paths
+ * of the test executables, which are placed in the profile's directory.
+ */
+pub fn compile_tests(pkg: &Package, deps: &PackageSet, resolve: &Resolve, options: &CompileOptions) -> CargoResult<Vec<Path>> {
+    debug!("compile_tests; pkg={}; deps={}", pkg, deps);
+
+    let cx = try!(compile(pkg, deps, resolve, options));
+
+    let root = pkg.get_root();
+    let kind = cx.kinds(pkg, true)[0];
+    let test_dir = cx.layout(kind).root.clone();
+
+    let mut harnesses = Vec::new();
 
-    // compile
     for target in pkg.get_targets().iter() {
-        // Only compile lib targets for dependencies
-        if primary || target.is_lib() {
-            try!(rustc(&pkg.get_root(), target, dest, deps_dir, primary))
+        let kind = if target.is_lib() { "lib" } else { "bin" };
+        harnesses.push((target.get_path().clone(), test_dir.join(format!("{}-{}", target.get_name(), kind))));
+    }
+
+    for path in try!(integration_tests(&root)).iter() {
+        let name = path.filestem_str().unwrap_or("test");
+        harnesses.push((path.clone(), test_dir.join(name)));
+    }
+
+    // The harnesses link against the native libraries of the package too
+    let mut native = try!(native_search_paths(pkg, deps, true, kind, &cx));
+
+    if pkg.get_manifest().get_build().is_some() {
+        match try!(BuildOutput::load(&cx.layout(kind).build.join(pkg.get_name()), pkg.to_str().as_slice())) {
+            Some(output) => native.push_all(output.rustc_args().as_slice()),
+            None => ()
         }
     }
 
-    Ok(())
+    for &(ref src, ref dest) in harnesses.iter() {
+        try!(rustc_test(pkg, src, dest, kind, native.as_slice(), &cx));
+    }
+
+    Ok(harnesses.move_iter().map(|(_, dest)| dest).collect())
 }
 
-fn mk_target(target: &Path) -> CargoResult<()> {
-    io::fs::mkdir_recursive(target, io::UserRWX)
-      .map_err(|_| other_error("could not create target directory"))
+/**
+ * The files directly inside the package's `tests` directory, relative to
+ * the package root.
+ */
+fn integration_tests(root: &Path) -> CargoResult<Vec<Path>> {
+    let dir = root.join("tests");
+
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut files: Vec<Path> = try!(io::fs::readdir(&dir).map_err(io_error)).move_iter()
+        .filter(|path| path.is_file() && path.extension_str() == Some("rs"))
+        .filter_map(|path| path.path_relative_from(root))
+        .collect();
+
+    files.sort();
+    Ok(files)
+}
+
+fn rustc_test(pkg: &Package, src: &Path, dest: &Path, kind: Kind, native: &[String], cx: &Context) -> CargoResult<()> {
+    let root = pkg.get_root();
+    log!(5, "root={}; src={}; dest={}; kind={}", root.display(), src.display(), dest.display(), kind);
+
+    let mut args = Vec::new();
+
+    args.push(src.display().to_str());
+    args.push("--test".to_str());
+    args.push("-o".to_str());
+    args.push(dest.display().to_str());
+    build_target_args(&mut args, kind, cx);
+    build_profile_args(&mut args, &cx.profile);
+    build_features_args(&mut args, pkg, cx);
+    args.push("-L".to_str());
+    args.push(cx.layout(kind).root.display().to_str());
+    build_deps_args(&mut args, kind, cx);
+    args.push_all(native);
+
+    let rustc = util::process("rustc")
+        .cwd(root.clone())
+        .args(args.as_slice())
+        .env("RUST_LOG", None);
+
+    rustc.exec().map_err(|e| rustc_to_cargo_err(rustc.get_args().as_slice(), &root, e))
 }
 
-fn rustc(root: &Path, target: &Target, dest: &Path, deps: &Path, verbose: bool) -> CargoResult<()> {
+/**
+ * Works out which of the package's targets are out of date, on each
+ * platform the package is needed on. Prints whether the package is fresh or
+ * is being compiled, and returns the job that compiles it, if any. The
+ * package's build script only runs when some of its targets are stale.
+ */
+fn prepare_job(pkg: &Package, deps: &PackageSet, primary: bool, cx: &mut Context) -> CargoResult<Option<Job>> {
+    debug!("prepare_job; pkg={}; targets={}", pkg, pkg.get_targets());
+
+    let files = try!(fingerprint::package_files(pkg));
+
+    let mut dep_fingerprints: Vec<String> = pkg.get_dependencies().iter()
+        .filter(|dep| dep.is_transitive())
+        .filter_map(|dep| cx.fingerprints.find_equiv(&dep.get_name()).map(|fp| format!("{}={}", dep.get_name(), fp)))
+        .collect();
+    dep_fingerprints.sort();
+
+    let mut pkg_fingerprint = Fingerprint::new();
+    let mut stages = Vec::new();
+
+    for kind in cx.kinds(pkg, primary).move_iter() {
+        let layout = cx.layout(kind);
+        let dest = if primary { &layout.root } else { &layout.deps };
+        let native = try!(native_search_paths(pkg, deps, primary, kind, cx));
+        let mut stale = Vec::new();
+
+        // Only compile lib targets for dependencies
+        for target in pkg.get_targets().iter().filter(|target| primary || target.is_lib()) {
+            let rustcs: Vec<ProcessBuilder> = target.rustc_crate_types().iter()
+                .map(|crate_type| prepare_rustc(pkg, target, *crate_type, dest, kind, native.as_slice(), cx))
+                .collect();
+
+            let mut fingerprint = Fingerprint::new();
+            fingerprint.add(cx.rustc_version.as_slice());
+
+            for rustc in rustcs.iter() {
+                fingerprint.add_all(rustc.get_args());
+            }
 
-    let crate_types = target.rustc_crate_types();
+            fingerprint.add_all(files.as_slice());
+            fingerprint.add_all(dep_fingerprints.as_slice());
 
-    for crate_type in crate_types.iter() {
-        log!(5, "root={}; target={}; crate_type={}; dest={}; deps={}; verbose={}",
-                root.display(), target, crate_type, dest.display(), deps.display(), verbose);
+            let hash = fingerprint.hash();
+            let target_kind = if target.is_lib() { "lib" } else { "bin" };
 
-        let rustc = prepare_rustc(root, target, *crate_type, dest, deps);
+            // A workspace member can be both the primary package and another
+            // member's dependency, compiled into different directories
+            let role = if primary { "-primary" } else { "" };
+            let path = layout.fingerprint.join(format!("{}-{}-{}{}", pkg.get_name(), target.get_name(), target_kind, role));
+
+            if !fingerprint::is_fresh(&path, hash.as_slice()) || !fingerprint::outputs_exist(target, dest) {
+                stale.push((rustcs, path, hash.clone()));
+            }
+
+            pkg_fingerprint.add(hash);
+        }
 
-        try!((if verbose {
-            rustc.exec()
-        } else {
-            rustc.exec_with_output().and(Ok(()))
-        }).map_err(|e| rustc_to_cargo_err(rustc.get_args().as_slice(), root, e)));
+        if !stale.is_empty() {
+            let build = match pkg.get_manifest().get_build() {
+                Some(build) => Some(BuildScript::new(pkg, build, &layout.build.join(pkg.get_name()), cx.triple(kind), cx.profile.get_name())),
+                None => None
+            };
+
+            stages.push(Stage { build: build, units: stale });
+        }
     }
 
-    Ok(())
+    cx.fingerprints.insert(pkg.get_name().to_str(), pkg_fingerprint.hash());
+
+    if stages.is_empty() {
+        if !cx.quiet {
+            println!("Fresh {}", pkg);
+        }
+
+        return Ok(None);
+    }
+
+    if !cx.quiet {
+        println!("Compiling {}", pkg);
+    }
+
+    Ok(Some(Job {
+        pkg: pkg.clone(),
+        stages: stages,
+        primary: primary
+    }))
+}
+
+/**
+ * The native search paths printed by the build scripts of the packages
+ * `pkg` depends on, directly or indirectly, so that it can link against the
+ * libraries they built.
+ */
+fn native_search_paths(pkg: &Package, deps: &PackageSet, primary: bool, kind: Kind, cx: &Context) -> CargoResult<Vec<String>> {
+    let mut ret = Vec::new();
+    let mut visited = HashSet::new();
+    let mut remaining = vec!((pkg, primary));
+
+    loop {
+        let (curr, primary) = match remaining.pop() {
+            Some(next) => next,
+            None => break
+        };
+
+        for dep in curr.get_dependencies().iter().filter(|dep| primary || dep.is_transitive()) {
+            let dep_pkg = match deps.iter().find(|other| other.get_name() == dep.get_name()) {
+                Some(dep_pkg) => dep_pkg,
+                None => continue
+            };
+
+            if !visited.insert(dep_pkg.get_name().to_str()) {
+                continue;
+            }
+
+            if dep_pkg.get_manifest().get_build().is_some() {
+                let dir = cx.layout(kind).build.join(dep_pkg.get_name());
+
+                match try!(BuildOutput::load(&dir, dep_pkg.to_str().as_slice())) {
+                    Some(output) => ret.push_all(output.search_path_args().as_slice()),
+                    None => ()
+                }
+            }
+
+            remaining.push((dep_pkg, false));
+        }
+    }
+
+    Ok(ret)
+}
+
+fn mk_target(target: &Path) -> CargoResult<()> {
+    io::fs::mkdir_recursive(target, io::UserRWX)
+      .map_err(|_| other_error("could not create target directory"))
 }
 
-fn prepare_rustc(root: &Path, target: &Target, crate_type: &'static str, dest: &Path, deps: &Path) -> ProcessBuilder {
+fn prepare_rustc(pkg: &Package, target: &Target, crate_type: &'static str, dest: &Path, kind: Kind,
+                 native: &[String], cx: &Context) -> ProcessBuilder {
     let mut args = Vec::new();
 
     build_base_args(&mut args, target, crate_type, dest);
-    build_deps_args(&mut args, deps);
+    build_target_args(&mut args, kind, cx);
+    build_profile_args(&mut args, &cx.profile);
+    build_features_args(&mut args, pkg, cx);
+    build_deps_args(&mut args, kind, cx);
+    args.push_all(native);
 
     util::process("rustc")
-        .cwd(root.clone())
+        .cwd(pkg.get_root())
         .args(args.as_slice())
         .env("RUST_LOG", None) // rustc is way too noisy
 }
@@ -92,16 +683,76 @@ fn build_base_args(into: &mut Args, target: &Target, crate_type: &'static str, d
     into.push(dest.display().to_str());
 }
 
-fn build_deps_args(dst: &mut Args, deps: &Path) {
+fn build_profile_args(into: &mut Args, profile: &Profile) {
+    if profile.get_opt_level() > 0 {
+        into.push("--opt-level".to_str());
+        into.push(profile.get_opt_level().to_str());
+    }
+
+    if profile.is_debug() {
+        into.push("-g".to_str());
+    }
+
+    into.push_all(profile.get_rustc_args());
+}
+
+fn build_target_args(into: &mut Args, kind: Kind, cx: &Context) {
+    if kind == KindHost {
+        return;
+    }
+
+    match cx.triple {
+        Some(ref triple) => {
+            into.push("--target".to_str());
+            into.push(triple.clone());
+        },
+        None => return
+    }
+
+    match cx.linker {
+        Some(ref linker) => {
+            into.push("-C".to_str());
+            into.push(format!("linker={}", linker));
+        },
+        None => ()
+    }
+
+    match cx.ar {
+        Some(ref ar) => {
+            into.push("-C".to_str());
+            into.push(format!("ar={}", ar));
+        },
+        None => ()
+    }
+}
+
+fn build_features_args(into: &mut Args, pkg: &Package, cx: &Context) {
+    for feature in cx.resolve.get_features(pkg.get_name()).iter() {
+        into.push("--cfg".to_str());
+        into.push(format!("feature=\"{}\"", feature));
+    }
+}
+
+fn build_deps_args(dst: &mut Args, kind: Kind, cx: &Context) {
     dst.push("-L".to_str());
-    dst.push(deps.display().to_str());
+    dst.push(cx.layout(kind).deps.display().to_str());
+
+    // Plugins are always compiled for the host
+    if kind == KindTarget && cx.triple.is_some() {
+        dst.push("-L".to_str());
+        dst.push(cx.host.deps.display().to_str());
+    }
 }
 
-fn rustc_to_cargo_err(args: &[String], cwd: &Path, err: CargoError) -> CargoError {
+pub fn rustc_to_cargo_err(args: &[String], cwd: &Path, err: CargoError) -> CargoError {
     let msg = {
         let output = match err {
             CargoError { kind: ProcessError(_, ref output), .. } => output,
-            _ => fail!("Bug! exec() returned an error other than a ProcessError")
+            // rustc could not be started at all
+            _ => {
+                let msg = format!("failed to execute: `rustc {}`; rustc could not be started", args.connect(" "));
+                return human_error(msg, format!("root={}", cwd.display()), err);
+            }
         };
 
         let mut msg = format!("failed to execute: `rustc {}`", args.connect(" "));
@@ -0,0 +1,157 @@
+/**
+ * Cargo test builds and runs a package's tests:
+ *
+ * 1. Resolve and fetch the package's dependencies, including its
+ *    development dependencies
+ * 2. Compile the package with `--test` for each of its targets, and each
+ *    file in its `tests` directory against the package's lib
+ * 3. Run every test executable, passing along the name filter if one was
+ *    given, and add up the results
+ *
+ * A workspace member is resolved with the rest of its workspace, within the
+ * lockfile at the root, and compiled into the shared target directory.
+ */
+
+use std::io;
+use std::str;
+use core::{Package,PackageSet};
+use core::resolver::Resolve;
+use sources::PathSource;
+use ops;
+use ops::CompileOptions;
+use ops::cargo_compile::resolve_and_fetch;
+use ops::workspace::{Workspace,dependencies_of};
+use util::{CargoResult,CargoError,Require,human_error,simple_human,process};
+use util::result::ProcessError;
+
+/**
+ * The counts reported by a test harness.
+ */
+#[deriving(PartialEq,Clone,Show)]
+struct TestResult {
+    passed: uint,
+    failed: uint,
+    ignored: uint
+}
+
+pub fn run_tests(manifest_path: &Path, filter: Option<&str>) -> CargoResult<()> {
+    log!(4, "run_tests; manifest-path={}; filter={}", manifest_path.display(), filter);
+
+    let mut options = ops::CompileOptions::new();
+    options.profile = "test".to_str();
+
+    let (package, packages, resolve) = match try!(Workspace::find(manifest_path)) {
+        Some(ws) => try!(resolve_member(&ws, manifest_path, &options)),
+        None => {
+            let package = try!(PathSource::read_package(manifest_path));
+            debug!("loaded package; package={}", package);
+
+            let (packages, resolve) = try!(resolve_and_fetch(&package, manifest_path, true, &options));
+            (package, packages, resolve)
+        }
+    };
+
+    let harnesses = try!(ops::compile_tests(&package, &PackageSet::new(packages.as_slice()), &resolve, &options));
+
+    let root = package.get_root();
+    let mut total = TestResult { passed: 0, failed: 0, ignored: 0 };
+    let mut crashed = Vec::new();
+
+    for harness in harnesses.iter() {
+        let name = harness.path_relative_from(&root).unwrap_or_else(|| harness.clone());
+        println!("Running {}", name.display());
+
+        let args: Vec<&str> = filter.iter().map(|f| *f).collect();
+        let output = match process(harness.display().to_str().as_slice()).cwd(root.clone()).args(args.as_slice()).exec_with_output() {
+            Ok(output) => output,
+            Err(CargoError { kind: ProcessError(_, Some(output)), .. }) => output,
+            Err(err) => return Err(human_error(format!("Couldn't run `{}`", name.display()), None::<&str>, err))
+        };
+
+        let stdout = str::from_utf8_lossy(output.output.as_slice()).to_str();
+        print!("{}", stdout);
+        let _ = io::stderr().write(output.error.as_slice());
+
+        match parse_result(stdout.as_slice()) {
+            Some(result) => {
+                total.passed += result.passed;
+                total.failed += result.failed;
+                total.ignored += result.ignored;
+            },
+            // The harness died before it could report its results
+            None => crashed.push(name.display().to_str())
+        }
+    }
+
+    println!("Test result: {} passed; {} failed; {} ignored", total.passed, total.failed, total.ignored);
+
+    if !crashed.is_empty() {
+        return Err(simple_human(format!("{} exited without reporting its results", crashed.connect(", "))));
+    }
+
+    match total.failed {
+        0 => Ok(()),
+        1 => Err(simple_human("1 test failed")),
+        n => Err(simple_human(format!("{} tests failed", n)))
+    }
+}
+
+/**
+ * Resolves the workspace member at `manifest_path` together with the rest
+ * of its workspace. Returns the member, set to compile into the shared
+ * target directory, and the packages its tests need.
+ */
+fn resolve_member(ws: &Workspace, manifest_path: &Path, options: &CompileOptions) -> CargoResult<(Package, Vec<Package>, Resolve)> {
+    let dir = manifest_path.dir_path();
+    let name = try!(ws.get_members().iter().find(|member| member.get_root() == dir).require(
+        simple_human(format!("`{}` is the root of a workspace; run `cargo test` in one of its members", ws.get_root().display()))))
+        .get_name().to_str();
+
+    let root = try!(ws.to_package([name.clone()], [], true));
+    let (packages, resolve) = try!(resolve_and_fetch(&root, &ws.get_manifest_path(), true, options));
+
+    let mut member = try!(packages.iter().find(|pkg| pkg.get_name() == name.as_slice()).require(
+        simple_human(format!("Could not resolve the workspace member `{}`", name)))).clone();
+    member.set_target_dir(&ws.get_root().join("target"));
+
+    let deps = dependencies_of(&member, packages.as_slice(), true);
+
+    Ok((member, deps, resolve))
+}
+
+/**
+ * Finds the `test result: ok. 3 passed; 0 failed; 0 ignored; 0 measured`
+ * line that every harness prints last.
+ */
+fn parse_result(output: &str) -> Option<TestResult> {
+    let line = some!(output.lines().find(|line| line.starts_with("test result:")));
+    let words: Vec<&str> = line.words().collect();
+
+    let count = |label: &str| -> Option<uint> {
+        words.iter().position(|word| word.trim_right_chars(';') == label)
+            .and_then(|i| if i > 0 { from_str(words[i - 1]) } else { None })
+    };
+
+    Some(TestResult {
+        passed: some!(count("passed")),
+        failed: some!(count("failed")),
+        ignored: count("ignored").unwrap_or(0)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{TestResult,parse_result};
+
+    #[test]
+    pub fn test_parse_result() {
+        let output = "running 3 tests\ntest a ... ok\ntest b ... FAILED\ntest c ... ignored\n\ntest result: FAILED. 1 passed; 1 failed; 1 ignored; 0 measured\n\n";
+
+        assert_eq!(parse_result(output), Some(TestResult { passed: 1, failed: 1, ignored: 1 }));
+    }
+
+    #[test]
+    pub fn test_parse_result_without_summary() {
+        assert_eq!(parse_result("running 1 test\n"), None);
+    }
+}
@@ -0,0 +1,94 @@
+/**
+ * Cargo update re-resolves the dependencies of a package and rewrites its
+ * lockfile:
+ *
+ * 1. Read the manifest and the existing lockfile
+ * 2. Drop the pins for the packages being updated (all of them, or one
+ *    package plus its transitive dependencies)
+ * 3. Update every source, keeping the remaining git sources at their locked
+ *    revisions
+ * 4. Resolve against the summaries the remaining pins permit
+ * 5. Write the new lockfile and report what changed
+ *
+ * In a workspace, the whole workspace is updated at once and the lockfile
+ * at its root is rewritten.
+ */
+
+use core::{Summary,Source};
+use core::resolver::resolve;
+use sources::PathSource;
+use ops::{Lockfile,LockedPackage,Workspace,load_lockfile,write_lockfile};
+use ops::cargo_compile::{git_references,sources_for,git_revisions,dependencies_to_resolve};
+use util::{CargoResult,Wrap,Require,simple_human};
+
+pub fn update(manifest_path: &Path, to_update: Option<&str>) -> CargoResult<()> {
+    log!(4, "update; manifest-path={}; package={}", manifest_path.display(), to_update);
+
+    let (package, manifest_path) = match try!(Workspace::find(manifest_path)) {
+        Some(ws) => (try!(ws.to_package([], [], true)), ws.get_manifest_path()),
+        None => (try!(PathSource::read_package(manifest_path)), manifest_path.clone())
+    };
+
+    let existing = try!(load_lockfile(&manifest_path));
+
+    // The pins that survive the update
+    let kept = match (existing.as_ref(), to_update) {
+        (Some(lock), Some(name)) => {
+            let names = try!(lock.transitive_names(name).require(
+                simple_human(format!("Package `{}` is not a dependency in Cargo.lock", name))));
+            Some(lock.without(names.as_slice()))
+        },
+        (None, Some(name)) => return Err(simple_human(format!("Cannot update `{}`; there is no Cargo.lock to update it in", name))),
+        (_, None) => None
+    };
+
+    let git_refs = git_references(&package, false, kept.as_ref());
+    let sources = try!(sources_for(&package, false, git_refs.as_slice()));
+
+    try!(sources.update().wrap("unable to update sources"));
+    let summaries = try!(sources.list().wrap("unable to list packages from source"));
+
+    let permitted: Vec<Summary> = summaries.move_iter()
+        .filter(|summary| kept.as_ref().map(|lock| lock.permits(summary.get_package_id())).unwrap_or(true))
+        .collect();
+
+    // The lockfile records the resolve with the default features
+    let features = try!(package.get_summary().expand_features([], true));
+    let deps = dependencies_to_resolve(&package, false, features.as_slice());
+    let resolved = try!(resolve(deps.as_slice(), &permitted).wrap("unable to resolve dependencies"));
+
+    try!(sources.download(resolved.as_slice()).wrap("unable to download packages"));
+    let packages = try!(sources.get(resolved.as_slice()).wrap("unable to get packages from source"));
+
+    let revisions = try!(git_revisions(git_refs.as_slice()));
+    let new_lock = Lockfile::new(&package, packages.as_slice(), revisions.as_slice());
+
+    print_changes(existing.as_ref(), &new_lock);
+
+    write_lockfile(&manifest_path, &new_lock).wrap("unable to write Cargo.lock")
+}
+
+fn print_changes(old: Option<&Lockfile>, new: &Lockfile) {
+    let old_packages = old.map(|lock| lock.get_packages()).unwrap_or(&[]);
+
+    for pkg in new.get_packages().iter() {
+        match find(old_packages, pkg.get_name()) {
+            Some(prev) => {
+                if prev.describe() != pkg.describe() {
+                    println!("Updating {} {} -> {}", pkg.get_name(), prev.describe(), pkg.describe());
+                }
+            },
+            None => println!("Adding {} {}", pkg.get_name(), pkg.describe())
+        }
+    }
+
+    for prev in old_packages.iter() {
+        if find(new.get_packages(), prev.get_name()).is_none() {
+            println!("Removing {} {}", prev.get_name(), prev.describe());
+        }
+    }
+}
+
+fn find<'a>(packages: &'a [LockedPackage], name: &str) -> Option<&'a LockedPackage> {
+    packages.iter().find(|pkg| pkg.get_name() == name)
+}
@@ -0,0 +1,139 @@
+use std::hash::sip::SipHasher;
+use std::hash::Hasher;
+use std::io::{fs,File};
+use std::os::consts::{EXE_SUFFIX,DLL_SUFFIX};
+use std::str;
+use core::{Package,Target};
+use ops::cargo_package::list_files;
+use util::{CargoResult,io_error,human_error,process};
+
+/**
+ * A fingerprint summarizes everything that goes into compiling a target:
+ * the rustc version, the flags it is invoked with, the modification times
+ * of the package's files and the fingerprints of the package's
+ * dependencies. A target only needs to be compiled again when its
+ * fingerprint changes.
+ *
+ * Fingerprints are stored in `target/.fingerprint`, one file per package
+ * and target, and are only written once the target compiled successfully.
+ * The primary package's files end in `-primary`, which keeps them apart
+ * from those of the same package compiled as a dependency.
+ */
+pub struct Fingerprint {
+    inputs: Vec<String>
+}
+
+impl Fingerprint {
+    pub fn new() -> Fingerprint {
+        Fingerprint { inputs: Vec::new() }
+    }
+
+    pub fn add<S: Str>(&mut self, input: S) {
+        self.inputs.push(input.as_slice().to_str());
+    }
+
+    pub fn add_all<S: Str>(&mut self, inputs: &[S]) {
+        for input in inputs.iter() {
+            self.add(input.as_slice());
+        }
+    }
+
+    pub fn hash(&self) -> String {
+        let hasher = SipHasher::new_with_keys(0, 0);
+        format!("{:016x}", hasher.hash(&self.inputs))
+    }
+}
+
+/**
+ * The output of `rustc -v`, so that upgrading the compiler invalidates
+ * every fingerprint.
+ */
+pub fn rustc_version() -> CargoResult<String> {
+    let output = try!(process("rustc").args(["-v"]).exec_with_output().map_err(|err|
+        human_error("Couldn't determine the version of rustc", None::<&str>, err)));
+
+    Ok(str::from_utf8_lossy(output.output.as_slice()).as_slice().trim().to_str())
+}
+
+/**
+ * The path and modification time of every file in the package.
+ */
+pub fn package_files(pkg: &Package) -> CargoResult<Vec<String>> {
+    let root = pkg.get_root();
+    let mut ret = Vec::new();
+
+    for file in try!(list_files(pkg)).iter() {
+        let stat = try!(fs::stat(&root.join(file)).map_err(io_error));
+        ret.push(format!("{}={}", file.display(), stat.modified));
+    }
+
+    Ok(ret)
+}
+
+pub fn is_fresh(path: &Path, hash: &str) -> bool {
+    match File::open(path).read_to_str() {
+        Ok(existing) => existing.as_slice() == hash,
+        Err(_) => false
+    }
+}
+
+/**
+ * Whether the files compiling `target` into `dest` produces are still
+ * there, so that a target whose output was deleted is compiled again even
+ * though its fingerprint did not change. Library names carry a hash, so any
+ * `lib<name>-*` file with the right extension will do.
+ */
+pub fn outputs_exist(target: &Target, dest: &Path) -> bool {
+    if !target.is_lib() {
+        return dest.join(format!("{}{}", target.get_name(), EXE_SUFFIX)).exists();
+    }
+
+    let files = match fs::readdir(dest) {
+        Ok(files) => files,
+        Err(_) => return false
+    };
+
+    let prefix = format!("lib{}", target.get_name());
+
+    target.rustc_crate_types().iter().all(|crate_type| {
+        let extension = match *crate_type {
+            "dylib" => DLL_SUFFIX,
+            "staticlib" => ".a",
+            _ => ".rlib"
+        };
+
+        files.iter().filter_map(|file| file.filename_str()).any(|name| {
+            name.ends_with(extension) &&
+                (name.starts_with(format!("{}-", prefix).as_slice()) || name == format!("{}{}", prefix, extension).as_slice())
+        })
+    })
+}
+
+pub fn write(path: &Path, hash: &str) -> CargoResult<()> {
+    log!(5, "writing fingerprint; path={}; hash={}", path.display(), hash);
+
+    let mut file = try!(File::create(path).map_err(io_error));
+    file.write_str(hash).map_err(io_error)
+}
+
+#[cfg(test)]
+mod test {
+    use super::Fingerprint;
+
+    fn fingerprint(inputs: &[&str]) -> String {
+        let mut fingerprint = Fingerprint::new();
+        fingerprint.add_all(inputs);
+        fingerprint.hash()
+    }
+
+    #[test]
+    pub fn test_fingerprint_is_stable() {
+        assert_eq!(fingerprint(["rustc 0.11.0", "src/foo.rs=1"]), fingerprint(["rustc 0.11.0", "src/foo.rs=1"]));
+    }
+
+    #[test]
+    pub fn test_fingerprint_covers_every_input() {
+        assert!(fingerprint(["rustc 0.11.0", "src/foo.rs=1"]) != fingerprint(["rustc 0.11.0", "src/foo.rs=2"]));
+        assert!(fingerprint(["rustc 0.11.0", "src/foo.rs=1"]) != fingerprint(["rustc 0.12.0", "src/foo.rs=1"]));
+    }
+}
@@ -0,0 +1,431 @@
+use std::io::File;
+use toml;
+use core::{Package,PackageId,Summary};
+use core::source::{GitKind,PathKind,SourceId};
+use sources::git::{Master,Branch,Tag,Rev,Other};
+use util::{CargoResult,io_error,human_error,simple_human,toml_error};
+
+/**
+ * A Lockfile records the exact result of resolving a package's
+ * dependencies, so that every build of the same commit uses the same code.
+ * It is stored next to the manifest as `Cargo.lock`.
+ *
+ * Packages are written sorted by name and version, one key per line, so
+ * that changes to the resolve produce small diffs.
+ *
+ * Development dependencies are not recorded; they are resolved within the
+ * locked versions whenever the package's tests are built. Likewise, the
+ * lockfile is written for the package's default features, and other sets
+ * of features are resolved within it.
+ */
+#[deriving(PartialEq,Clone,Show)]
+pub struct Lockfile {
+    name: String,
+    version: String,
+    // The root package's requirements at the time the lockfile was written
+    dependencies: Vec<String>,
+    // The sources of those requirements, with the git reference asked for
+    sources: Vec<String>,
+    packages: Vec<LockedPackage>
+}
+
+#[deriving(PartialEq,Clone,Show)]
+pub struct LockedPackage {
+    name: String,
+    version: String,
+    // The namespace of the resolved PackageId
+    source: String,
+    // The exact revision of git sources
+    revision: Option<String>,
+    // The names of the packages this package depends on
+    dependencies: Vec<String>
+}
+
+#[deriving(Decodable)]
+struct TomlLockfile {
+    root: TomlLockedRoot,
+    package: Option<Vec<TomlLockedPackage>>
+}
+
+#[deriving(Decodable)]
+struct TomlLockedRoot {
+    name: String,
+    version: String,
+    dependencies: Vec<String>,
+    sources: Option<Vec<String>>
+}
+
+#[deriving(Decodable)]
+struct TomlLockedPackage {
+    name: String,
+    version: String,
+    source: String,
+    revision: Option<String>,
+    dependencies: Option<Vec<String>>
+}
+
+impl Lockfile {
+    /**
+     * Builds a lockfile for `package` from its resolved dependencies.
+     * `revisions` maps git URLs (as strings) to the revision they were
+     * checked out at.
+     */
+    pub fn new(package: &Package, resolved: &[Package], revisions: &[(String, String)]) -> Lockfile {
+        let mut packages: Vec<LockedPackage> = resolved.iter().map(|pkg| {
+            let id = pkg.get_package_id();
+            let source = id.get_namespace().to_str();
+            let revision = revisions.iter()
+                .find(|&&(ref url, _)| *url == source)
+                .map(|&(_, ref rev)| rev.clone());
+
+            LockedPackage {
+                name: id.get_name().to_str(),
+                version: id.get_version().to_str(),
+                source: source,
+                revision: revision,
+                dependencies: dependency_names(pkg)
+            }
+        }).collect();
+
+        packages.sort_by(|a, b| {
+            (a.name.as_slice(), a.version.as_slice()).cmp(&(b.name.as_slice(), b.version.as_slice()))
+        });
+
+        Lockfile {
+            name: package.get_name().to_str(),
+            version: package.get_version().to_str(),
+            dependencies: dependency_strs(package),
+            sources: source_strs(package),
+            packages: packages
+        }
+    }
+
+    pub fn parse(contents: &[u8]) -> CargoResult<Lockfile> {
+        let root = try!(toml::parse_from_bytes(contents).map_err(|_|
+            simple_human("Cargo.lock is not valid Toml")));
+
+        let lock: TomlLockfile = try!(toml::from_toml(root).map_err(|e|
+            human_error("Cargo.lock is not a valid lockfile", "", toml_error("could not decode lockfile", e))));
+
+        let packages = lock.package.unwrap_or_else(|| Vec::new()).move_iter().map(|pkg| {
+            LockedPackage {
+                name: pkg.name,
+                version: pkg.version,
+                source: pkg.source,
+                revision: pkg.revision,
+                dependencies: pkg.dependencies.unwrap_or_else(|| Vec::new())
+            }
+        }).collect();
+
+        Ok(Lockfile {
+            name: lock.root.name,
+            version: lock.root.version,
+            dependencies: lock.root.dependencies,
+            sources: lock.root.sources.unwrap_or_else(|| Vec::new()),
+            packages: packages
+        })
+    }
+
+    pub fn get_packages<'a>(&'a self) -> &'a [LockedPackage] {
+        self.packages.as_slice()
+    }
+
+    /**
+     * A lockfile is only reused while the manifest's requirements, and the
+     * sources and git references they come from, are the ones it was
+     * written for.
+     */
+    pub fn is_fresh_for(&self, package: &Package) -> bool {
+        self.dependencies == dependency_strs(package) && self.sources == source_strs(package)
+    }
+
+    /**
+     * Returns the revision a git source was locked to, if any.
+     */
+    pub fn revision_for<'a>(&'a self, url: &str) -> Option<&'a str> {
+        self.packages.iter()
+            .find(|pkg| pkg.source.as_slice() == url && pkg.revision.is_some())
+            .and_then(|pkg| pkg.revision.as_ref().map(|rev| rev.as_slice()))
+    }
+
+    /**
+     * Maps every locked package onto the summaries available from the
+     * sources. Returns None if any locked package is no longer available,
+     * in which case the dependencies must be resolved again.
+     */
+    pub fn locked_ids(&self, summaries: &[Summary]) -> Option<Vec<PackageId>> {
+        let mut ret = Vec::new();
+
+        for locked in self.packages.iter() {
+            let summary = some!(summaries.iter().find(|summary| locked.matches(summary.get_package_id())));
+            ret.push(summary.get_package_id().clone());
+        }
+
+        Some(ret)
+    }
+
+    /**
+     * Returns a copy of the lockfile without the named packages, so that
+     * they are free to be resolved again.
+     */
+    pub fn without(&self, names: &[String]) -> Lockfile {
+        Lockfile {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            dependencies: self.dependencies.clone(),
+            sources: self.sources.clone(),
+            packages: self.packages.iter()
+                .filter(|pkg| !names.contains(&pkg.name))
+                .map(|pkg| pkg.clone())
+                .collect()
+        }
+    }
+
+    /**
+     * A locked package pins its name to a single version; any other package
+     * id is permitted.
+     */
+    pub fn permits(&self, id: &PackageId) -> bool {
+        self.packages.iter()
+            .filter(|pkg| pkg.name.as_slice() == id.get_name())
+            .all(|pkg| pkg.matches(id))
+    }
+
+    /**
+     * Returns the named package followed by everything it depends on,
+     * directly or indirectly, according to the lockfile. Returns None if
+     * the package is not locked.
+     */
+    pub fn transitive_names(&self, name: &str) -> Option<Vec<String>> {
+        let mut ret = Vec::new();
+        let mut remaining = vec!(name.to_str());
+
+        if !self.packages.iter().any(|pkg| pkg.name.as_slice() == name) {
+            return None;
+        }
+
+        loop {
+            let curr = match remaining.pop() {
+                Some(curr) => curr,
+                None => return Some(ret)
+            };
+
+            if ret.contains(&curr) {
+                continue;
+            }
+
+            for pkg in self.packages.iter().filter(|pkg| pkg.name == curr) {
+                remaining.push_all(pkg.dependencies.as_slice());
+            }
+
+            ret.push(curr);
+        }
+    }
+
+    pub fn to_toml(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("[root]\n");
+        out.push_str(format!("name = {}\n", quote(self.name.as_slice())).as_slice());
+        out.push_str(format!("version = {}\n", quote(self.version.as_slice())).as_slice());
+        out.push_str(format!("dependencies = {}\n", array(self.dependencies.as_slice())).as_slice());
+
+        if !self.sources.is_empty() {
+            out.push_str(format!("sources = {}\n", array(self.sources.as_slice())).as_slice());
+        }
+
+        for pkg in self.packages.iter() {
+            out.push_str("\n[[package]]\n");
+            out.push_str(format!("name = {}\n", quote(pkg.name.as_slice())).as_slice());
+            out.push_str(format!("version = {}\n", quote(pkg.version.as_slice())).as_slice());
+            out.push_str(format!("source = {}\n", quote(pkg.source.as_slice())).as_slice());
+
+            match pkg.revision {
+                Some(ref rev) => out.push_str(format!("revision = {}\n", quote(rev.as_slice())).as_slice()),
+                None => ()
+            }
+
+            if !pkg.dependencies.is_empty() {
+                out.push_str(format!("dependencies = {}\n", array(pkg.dependencies.as_slice())).as_slice());
+            }
+        }
+
+        out
+    }
+}
+
+impl LockedPackage {
+    pub fn get_name<'a>(&'a self) -> &'a str {
+        self.name.as_slice()
+    }
+
+    pub fn get_version<'a>(&'a self) -> &'a str {
+        self.version.as_slice()
+    }
+
+    pub fn get_revision<'a>(&'a self) -> Option<&'a str> {
+        self.revision.as_ref().map(|rev| rev.as_slice())
+    }
+
+    /**
+     * Describes the locked version (and revision, for git packages) for
+     * humans, e.g. `v0.5.0 (rev 0123abcd)`.
+     */
+    pub fn describe(&self) -> String {
+        match self.revision {
+            Some(ref rev) => format!("v{} (rev {})", self.version, rev),
+            None => format!("v{}", self.version)
+        }
+    }
+
+    pub fn matches(&self, id: &PackageId) -> bool {
+        self.name.as_slice() == id.get_name() &&
+            self.version == id.get_version().to_str() &&
+            self.source == id.get_namespace().to_str()
+    }
+}
+
+pub fn load_lockfile(manifest_path: &Path) -> CargoResult<Option<Lockfile>> {
+    let path = lockfile_path(manifest_path);
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut file = try!(File::open(&path).map_err(io_error));
+    let data = try!(file.read_to_end().map_err(io_error));
+
+    Lockfile::parse(data.as_slice()).map(|lock| Some(lock))
+}
+
+pub fn write_lockfile(manifest_path: &Path, lock: &Lockfile) -> CargoResult<()> {
+    let path = lockfile_path(manifest_path);
+    log!(5, "writing lockfile; path={}", path.display());
+
+    let mut file = try!(File::create(&path).map_err(io_error));
+    file.write_str(lock.to_toml().as_slice()).map_err(io_error)
+}
+
+fn lockfile_path(manifest_path: &Path) -> Path {
+    manifest_path.dir_path().join("Cargo.lock")
+}
+
+fn dependency_strs(package: &Package) -> Vec<String> {
+    let mut deps: Vec<String> = package.get_dependencies().iter()
+        .filter(|dep| dep.is_transitive())
+        .map(|dep| format!("{} {}", dep.get_name(), dep.get_version_req()))
+        .collect();
+
+    deps.sort();
+    deps
+}
+
+fn source_strs(package: &Package) -> Vec<String> {
+    let mut sources: Vec<String> = package.get_sources().iter().map(source_str).collect();
+
+    sources.sort();
+    sources
+}
+
+/**
+ * Describes a source as it was asked for in the manifest, e.g.
+ * `git https://github.com/example/baz tag=v1.0.0`.
+ */
+fn source_str(source_id: &SourceId) -> String {
+    match source_id.kind {
+        PathKind => format!("path {}", source_id.url),
+        GitKind(Master) => format!("git {}", source_id.url),
+        GitKind(Branch(ref branch)) => format!("git {} branch={}", source_id.url, branch),
+        GitKind(Tag(ref tag)) => format!("git {} tag={}", source_id.url, tag),
+        GitKind(Rev(ref rev)) => format!("git {} rev={}", source_id.url, rev),
+        GitKind(Other(ref reference)) => format!("git {} ref={}", source_id.url, reference)
+    }
+}
+
+fn dependency_names(package: &Package) -> Vec<String> {
+    let mut names: Vec<String> = package.get_dependencies().iter()
+        .filter(|dep| dep.is_transitive())
+        .map(|dep| dep.get_name().to_str())
+        .collect();
+
+    names.sort();
+    names
+}
+
+fn quote(s: &str) -> String {
+    let mut ret = String::from_str("\"");
+
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            c => ret.push_char(c)
+        }
+    }
+
+    ret.push_char('"');
+    ret
+}
+
+fn array(values: &[String]) -> String {
+    if values.is_empty() {
+        return "[]".to_str();
+    }
+
+    let lines: Vec<String> = values.iter().map(|v| format!("  {}", quote(v.as_slice()))).collect();
+    format!("[\n{}\n]", lines.connect(",\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Lockfile,LockedPackage};
+
+    fn lockfile() -> Lockfile {
+        Lockfile {
+            name: "foo".to_str(),
+            version: "0.5.0".to_str(),
+            dependencies: vec!("bar = 0.5.0".to_str(), "baz = 1.0.0".to_str()),
+            sources: vec!("git https://github.com/example/baz tag=v1.0.0".to_str()),
+            packages: vec!(
+                LockedPackage {
+                    name: "bar".to_str(),
+                    version: "0.5.0".to_str(),
+                    source: "file:/projects/bar".to_str(),
+                    revision: None,
+                    dependencies: vec!("baz".to_str())
+                },
+                LockedPackage {
+                    name: "baz".to_str(),
+                    version: "1.0.0".to_str(),
+                    source: "https://github.com/example/baz".to_str(),
+                    revision: Some("0123456789abcdef".to_str()),
+                    dependencies: vec!()
+                })
+        }
+    }
+
+    #[test]
+    pub fn test_lockfile_round_trip() {
+        let lock = lockfile();
+        let parsed = Lockfile::parse(lock.to_toml().as_bytes()).unwrap();
+
+        assert_eq!(parsed, lock);
+    }
+
+    #[test]
+    pub fn test_lockfile_revision_for() {
+        let lock = lockfile();
+
+        assert_eq!(lock.revision_for("https://github.com/example/baz"), Some("0123456789abcdef"));
+        assert_eq!(lock.revision_for("file:/projects/bar"), None);
+    }
+
+    #[test]
+    pub fn test_lockfile_transitive_names() {
+        let lock = lockfile();
+
+        assert_eq!(lock.transitive_names("bar"), Some(vec!("bar".to_str(), "baz".to_str())));
+        assert_eq!(lock.transitive_names("baz"), Some(vec!("baz".to_str())));
+        assert_eq!(lock.transitive_names("quux"), None);
+    }
+}
@@ -1,7 +1,27 @@
-pub use self::cargo_compile::compile;
-pub use self::cargo_read_manifest::{read_manifest,read_package};
-pub use self::cargo_rustc::compile_packages;
+pub use self::cargo_compile::{compile,CompileOptions};
+pub use self::cargo_read_manifest::{read_manifest,read_package,read_packages,find_manifests};
+pub use self::cargo_rustc::{compile_packages,compile_tests,output_dir,Artifact};
+pub use self::cargo_update::update;
+pub use self::cargo_package::package;
+pub use self::cargo_publish::publish;
+pub use self::cargo_test::run_tests;
+pub use self::cargo_new::{new,NewOptions};
+pub use self::cargo_run::run;
+pub use self::cargo_clean::{clean,CleanOptions};
+pub use self::workspace::{Workspace,read_member};
+pub use self::lockfile::{Lockfile,LockedPackage,load_lockfile,write_lockfile};
 
 mod cargo_compile;
 mod cargo_read_manifest;
 mod cargo_rustc;
+mod cargo_update;
+mod cargo_package;
+mod cargo_publish;
+mod cargo_test;
+mod cargo_new;
+mod cargo_run;
+mod cargo_clean;
+mod lockfile;
+mod fingerprint;
+mod build_script;
+mod workspace;
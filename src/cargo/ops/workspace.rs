@@ -0,0 +1,192 @@
+use std::collections::HashMap;
+use core::{Dependency,Manifest,Package,PackageId,Profile,Summary};
+use core::source::{PathKind,SourceId};
+use sources::PathSource;
+use sources::path::namespace;
+use util::{CargoResult,Require,human_error,simple_human};
+use util::important_paths::{find_workspace_root,workspace_members,is_package_manifest};
+
+/**
+ * A workspace is a set of packages that are resolved together, share one
+ * Cargo.lock and are compiled into one target directory, so that their
+ * common dependencies are only compiled once. Its root manifest lists the
+ * member directories:
+ *
+ *   [workspace]
+ *   members = ["core", "cli"]
+ *
+ * The root manifest may describe a package as well, which is then a member
+ * too.
+ */
+#[deriving(Clone,Show)]
+pub struct Workspace {
+    root: Path,
+    members: Vec<Package>
+}
+
+// The name of the package standing in for the workspace when resolving
+static ROOT_NAME: &'static str = "workspace";
+
+impl Workspace {
+    /**
+     * The workspace the package at `manifest_path` belongs to, if any.
+     */
+    pub fn find(manifest_path: &Path) -> CargoResult<Option<Workspace>> {
+        match try!(find_workspace_root(&manifest_path.dir_path())) {
+            Some(root) => Workspace::load(&root).map(|ws| Some(ws)),
+            None => Ok(None)
+        }
+    }
+
+    pub fn load(root: &Path) -> CargoResult<Workspace> {
+        let manifest_path = root.join("Cargo.toml");
+        let paths = try!(try!(workspace_members(&manifest_path)).require(
+            simple_human(format!("`{}` does not define a workspace", manifest_path.display()))));
+
+        let target_dir = root.join("target");
+        let mut members = Vec::new();
+
+        // A root manifest without a `[project]` only lists the members
+        if try!(is_package_manifest(&manifest_path)) {
+            members.push(try!(PathSource::read_package(&manifest_path).map_err(|err|
+                human_error(format!("Could not read the workspace root package at `{}`", manifest_path.display()), None::<&str>, err))));
+        }
+
+        for path in paths.iter() {
+            let dir = root.join(path.as_slice());
+            let pkg = try!(PathSource::read_package(&dir.join("Cargo.toml")).map_err(|err|
+                human_error(format!("Could not read the workspace member at `{}`", dir.display()), format!("root={}", root.display()), err)));
+
+            members.push(pkg);
+        }
+
+        for member in members.mut_iter() {
+            member.set_target_dir(&target_dir);
+        }
+
+        Ok(Workspace { root: root.clone(), members: members })
+    }
+
+    pub fn get_root<'a>(&'a self) -> &'a Path {
+        &self.root
+    }
+
+    pub fn get_manifest_path(&self) -> Path {
+        self.root.join("Cargo.toml")
+    }
+
+    pub fn get_members<'a>(&'a self) -> &'a [Package] {
+        self.members.as_slice()
+    }
+
+    pub fn get_member<'a>(&'a self, name: &str) -> Option<&'a Package> {
+        self.members.iter().find(|member| member.get_name() == name)
+    }
+
+    /**
+     * Whether the root manifest only lists the members.
+     */
+    pub fn is_virtual(&self) -> bool {
+        !self.members.iter().any(|member| member.get_root() == self.root)
+    }
+
+    /**
+     * A package that depends on every member at its exact version, so that
+     * resolving its dependencies resolves the whole workspace at once. The
+     * members named in `selected` ask for `features`, and for their default
+     * features only if `default_features` is set. Development dependencies
+     * of the members are its own development dependencies.
+     */
+    pub fn to_package(&self, selected: &[String], features: &[String], default_features: bool) -> CargoResult<Package> {
+        let mut deps = Vec::new();
+        let mut sources = Vec::new();
+        let mut dev_sources = Vec::new();
+
+        for member in self.members.iter() {
+            let dep = Dependency::exact(member.get_name(), member.get_version());
+
+            deps.push(if selected.contains(&member.get_name().to_str()) {
+                dep.features(Vec::from_slice(features)).default_features(default_features)
+            } else {
+                dep
+            });
+
+            for dev in member.get_dependencies().iter().filter(|dep| !dep.is_transitive()) {
+                if !deps.contains(dev) {
+                    deps.push(dev.clone());
+                }
+            }
+
+            // The root is always searched for packages already
+            if member.get_root() != self.root {
+                sources.push(SourceId::new(PathKind, try!(namespace(&member.get_root()))));
+            }
+
+            sources.push_all(member.get_sources());
+            dev_sources.push_all(member.get_dev_sources());
+        }
+
+        // The requested features are passed on to the members, but they must
+        // be features of the root too to be requested at all
+        let root_features: HashMap<String, Vec<String>> = features.iter()
+            .map(|feature| (feature.clone(), Vec::new()))
+            .collect();
+
+        let id = PackageId::new(ROOT_NAME, "0.0.0", &try!(namespace(&self.root)));
+        let summary = Summary::new(&id, deps.as_slice()).features(root_features);
+        let manifest = Manifest::new(&summary, [], [], &Path::new("target"), sources, Profile::default_profiles(), None)
+            .dev_sources(dev_sources);
+
+        Ok(Package::new(manifest, &self.get_manifest_path()))
+    }
+}
+
+/**
+ * Reads the package at `manifest_path`, compiling into the target directory
+ * of its workspace if it belongs to one.
+ */
+pub fn read_member(manifest_path: &Path) -> CargoResult<Package> {
+    let mut package = try!(PathSource::read_package(manifest_path));
+
+    match try!(find_workspace_root(&manifest_path.dir_path())) {
+        Some(root) => package.set_target_dir(&root.join("target")),
+        None => ()
+    }
+
+    Ok(package)
+}
+
+/**
+ * The packages `pkg` needs to be compiled, directly or indirectly, among
+ * the packages resolved for its workspace. Its own development
+ * dependencies are included if `include_dev` is set, to compile its tests.
+ */
+pub fn dependencies_of(pkg: &Package, packages: &[Package], include_dev: bool) -> Vec<Package> {
+    let mut ret: Vec<Package> = Vec::new();
+    let mut remaining = vec!(pkg.clone());
+
+    loop {
+        let curr = match remaining.pop() {
+            Some(curr) => curr,
+            None => break
+        };
+
+        let is_root = curr.get_package_id() == pkg.get_package_id();
+
+        for dep in curr.get_dependencies().iter().filter(|dep| dep.is_transitive() || (include_dev && is_root)) {
+            if ret.iter().any(|other| other.get_name() == dep.get_name()) {
+                continue;
+            }
+
+            match packages.iter().find(|other| other.get_name() == dep.get_name()) {
+                Some(dep_pkg) => {
+                    ret.push(dep_pkg.clone());
+                    remaining.push(dep_pkg.clone());
+                },
+                None => ()
+            }
+        }
+    }
+
+    ret
+}
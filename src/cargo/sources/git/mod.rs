@@ -1,4 +1,5 @@
 pub use self::utils::{GitRemote,GitDatabase,GitCheckout};
+pub use self::utils::{GitReference,Master,Branch,Tag,Rev,Other};
 pub use self::source::{GitSource};
 mod utils;
 mod source;
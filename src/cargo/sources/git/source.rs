@@ -2,26 +2,69 @@ use ops;
 use url;
 use core::source::Source;
 use core::{Package,PackageId,Summary};
-use util::CargoResult;
-use sources::git::utils::{GitReference,GitRemote,Master,Other};
+use util::{CargoResult,simple_human};
+use sources::git::utils::{GitReference,GitRemote,Master};
 use std::fmt;
 use std::fmt::{Show,Formatter};
 
+/**
+ * A GitSource provides the packages of a git repository, checked out at a
+ * branch, tag or revision: one for each Cargo.toml in the checkout, so that
+ * a repository may hold several packages in subdirectories. Each revision
+ * is checked out into its own directory under `checkouts_path`, named after
+ * the revision that the reference resolved to.
+ */
 pub struct GitSource {
     remote: GitRemote,
     reference: GitReference,
     db_path: Path,
-    checkout_path: Path
+    checkouts_path: Path
 }
 
 impl GitSource {
-    pub fn new(remote: GitRemote, reference: String, db: Path, checkout: Path) -> GitSource {
-        GitSource { remote: remote, reference: GitReference::for_str(reference), db_path: db, checkout_path: checkout }
+    pub fn new(remote: GitRemote, reference: GitReference, db: Path, checkouts: Path) -> GitSource {
+        GitSource { remote: remote, reference: reference, db_path: db, checkouts_path: checkouts }
     }
 
     pub fn get_namespace<'a>(&'a self) -> &'a url::Url {
         self.remote.get_url()
     }
+
+    /**
+     * The revision the reference currently resolves to in the local
+     * database. Requires `update` to have been called.
+     */
+    pub fn get_revision(&self) -> CargoResult<String> {
+        self.remote.db_at(&self.db_path).rev_for(self.reference.as_slice())
+    }
+
+    fn checkout_path(&self) -> CargoResult<Path> {
+        let revision = try!(self.get_revision());
+        Ok(self.checkouts_path.join(revision.as_slice()))
+    }
+
+    /**
+     * Every package in the checkout. Packages are found by name, so two
+     * packages with the same name in one repository are an error. Nested
+     * manifests that cannot be read are skipped, with a warning if `warn`
+     * is set; `get` runs after `list` and so leaves the warnings to it.
+     */
+    fn read_packages(&self, warn: bool) -> CargoResult<Vec<Package>> {
+        let path = try!(self.checkout_path());
+        let packages = try!(ops::read_packages(&path, self.get_namespace(), warn));
+
+        for (i, pkg) in packages.iter().enumerate() {
+            match packages.slice_to(i).iter().find(|other| other.get_name() == pkg.get_name()) {
+                Some(other) => {
+                    return Err(simple_human(format!("The {} contains more than one package named `{}`, at `{}` and `{}`",
+                                                    self, pkg.get_name(), relative(other, &path), relative(pkg, &path))));
+                },
+                None => ()
+            }
+        }
+
+        Ok(packages)
+    }
 }
 
 impl Show for GitSource {
@@ -30,7 +73,7 @@ impl Show for GitSource {
 
         match self.reference {
             Master => Ok(()),
-            Other(ref reference) => write!(f, " ({})", reference)
+            ref reference => write!(f, " ({})", reference)
         }
     }
 }
@@ -40,15 +83,16 @@ impl Source for GitSource {
         println!("Updating git repository `{}`", self.remote.get_url());
         log!(5, "updating git source `{}`", self.remote);
         let repo = try!(self.remote.checkout(&self.db_path));
-        try!(repo.copy_to(self.reference.as_slice(), &self.checkout_path));
+        let revision = try!(repo.rev_for(self.reference.as_slice()));
+        try!(repo.copy_to(revision.as_slice(), &self.checkouts_path.join(revision.as_slice())));
 
         Ok(())
     }
 
     fn list(&self) -> CargoResult<Vec<Summary>> {
         log!(5, "listing summaries in git source `{}`", self.remote);
-        let pkg = try!(read_manifest(&self.checkout_path, self.get_namespace()));
-        Ok(vec!(pkg.get_summary().clone()))
+        let packages = try!(self.read_packages(true));
+        Ok(packages.iter().map(|pkg| pkg.get_summary().clone()).collect())
     }
 
     fn download(&self, _: &[PackageId]) -> CargoResult<()> {
@@ -57,18 +101,16 @@ impl Source for GitSource {
 
     fn get(&self, package_ids: &[PackageId]) -> CargoResult<Vec<Package>> {
         log!(5, "getting packages for package ids `{}` from `{}`", package_ids, self.remote);
-        // TODO: Support multiple manifests per repo
-        let pkg = try!(read_manifest(&self.checkout_path, self.remote.get_url()));
+        let packages = try!(self.read_packages(false));
 
-        if package_ids.iter().any(|pkg_id| pkg_id == pkg.get_package_id()) {
-            Ok(vec!(pkg))
-        } else {
-            Ok(vec!())
-        }
+        Ok(packages.move_iter()
+            .filter(|pkg| package_ids.iter().any(|pkg_id| pkg_id == pkg.get_package_id()))
+            .collect())
     }
 }
 
-fn read_manifest(path: &Path, url: &url::Url) -> CargoResult<Package> {
-    let path = path.join("Cargo.toml");
-    ops::read_package(&path, url)
+fn relative(pkg: &Package, checkout: &Path) -> String {
+    pkg.get_manifest_path().path_relative_from(checkout)
+        .unwrap_or_else(|| pkg.get_manifest_path().clone())
+        .display().to_str()
 }
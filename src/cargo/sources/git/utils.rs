@@ -7,9 +7,16 @@ use std::io::{UserDir,AllPermissions};
 use std::io::fs::{mkdir_recursive,rmdir_recursive,chmod};
 use serialize::{Encodable,Encoder};
 
+/**
+ * GitReference names the point in a repository's history that a git
+ * dependency should be checked out at.
+ */
 #[deriving(PartialEq,Clone,Encodable)]
 pub enum GitReference {
     Master,
+    Branch(String),
+    Tag(String),
+    Rev(String),
     Other(String)
 }
 
@@ -21,13 +28,21 @@ impl GitReference {
             Other(string.as_slice().to_str())
         }
     }
+
+    pub fn branch<S: Str>(string: S) -> GitReference {
+        if string.as_slice() == "master" {
+            Master
+        } else {
+            Branch(string.as_slice().to_str())
+        }
+    }
 }
 
 impl Str for GitReference {
     fn as_slice<'a>(&'a self) -> &'a str {
         match *self {
             Master => "master",
-            Other(ref string) => string.as_slice()
+            Branch(ref string) | Tag(ref string) | Rev(ref string) | Other(ref string) => string.as_slice()
         }
     }
 }
@@ -166,7 +181,15 @@ impl GitRemote {
             try!(self.clone_into(into));
         }
 
-        Ok(GitDatabase { remote: self.clone(), path: into.clone(), verbose: self.verbose })
+        Ok(self.db_at(into))
+    }
+
+    /**
+     * Returns the GitDatabase that `checkout` would produce at `path`,
+     * without touching the network. The database must already exist.
+     */
+    pub fn db_at(&self, path: &Path) -> GitDatabase {
+        GitDatabase { remote: self.clone(), path: path.clone(), verbose: self.verbose }
     }
 
     fn fetch_into(&self, path: &Path) -> CargoResult<()> {
@@ -205,8 +228,12 @@ impl GitDatabase {
         Ok(checkout)
     }
 
+    /**
+     * Resolves a branch, tag or revision to the commit it points at.
+     * Annotated tags are peeled to their commit by the `^0` suffix.
+     */
     pub fn rev_for<S: Str>(&self, reference: S) -> CargoResult<String> {
-        Ok(git_output!(self.path, self.verbose, "rev-parse {}", reference.as_slice()))
+        Ok(git_output!(self.path, self.verbose, "rev-parse {}^0", reference.as_slice()))
     }
 
 }
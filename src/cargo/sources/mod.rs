@@ -1,5 +1,7 @@
 pub use self::path::PathSource;
 pub use self::git::GitSource;
+pub use self::registry::RegistrySource;
 
 pub mod path;
 pub mod git;
+pub mod registry;
@@ -4,19 +4,42 @@ use core::{Package,PackageId,Summary};
 use core::source::Source;
 use ops;
 use url;
-use util::{CargoResult,simple_human,io_error,realpath};
+use util::{CargoResult,simple_human,io_error,realpath,warning};
+use util::important_paths::{workspace_members,is_package_manifest};
 
 /* 
  * TODO: Consider whether it may be more appropriate for a PathSource to only
  * take in a single path vs. a vec of paths. The pros / cons are unknown at
  * this point.
  */
-pub struct PathSource { paths: Vec<Path> }
+pub struct PathSource {
+    // Directories whose own Cargo.toml is read
+    paths: Vec<Path>,
+    // Directories searched recursively for manifests
+    roots: Vec<Path>,
+    // Names or root-relative paths of directories not to search
+    ignored: Vec<String>,
+    // How many levels below a root are searched
+    max_depth: uint
+}
+
+// The default number of directory levels searched below each root
+pub static DEFAULT_DEPTH: uint = 3;
 
 impl PathSource {
     pub fn new(paths: Vec<Path>) -> PathSource {
         log!(5, "new; paths={}", display(paths.as_slice()));
-        PathSource { paths: paths }
+        PathSource { paths: paths, roots: vec!(), ignored: vec!(), max_depth: DEFAULT_DEPTH }
+    }
+
+    /**
+     * Also provides the packages found anywhere below `roots`, at most
+     * `max_depth` directories down, except in the directories named by
+     * `ignored`.
+     */
+    pub fn recursive(self, roots: Vec<Path>, ignored: Vec<String>, max_depth: uint) -> PathSource {
+        log!(5, "recursive; roots={}; ignored={}; max_depth={}", display(roots.as_slice()), ignored, max_depth);
+        PathSource { roots: roots, ignored: ignored, max_depth: max_depth, ..self }
     }
 
     pub fn read_package(path: &Path) -> CargoResult<Package> {
@@ -28,6 +51,62 @@ impl PathSource {
 
         ops::read_package(path, &namespace)
     }
+
+    /**
+     * The manifests of the configured paths, followed by those found below
+     * the roots. A root that cannot be searched is skipped.
+     */
+    fn manifests(&self, warn: bool) -> Vec<Path> {
+        let mut ret: Vec<Path> = self.paths.iter().map(|path| path.join("Cargo.toml")).collect();
+
+        for root in self.roots.iter() {
+            match ops::find_manifests(root, self.ignored.as_slice(), Some(self.max_depth), warn) {
+                Ok(manifests) => {
+                    for manifest in manifests.move_iter() {
+                        if !ret.contains(&manifest) {
+                            ret.push(manifest);
+                        }
+                    }
+                },
+                Err(e) => {
+                    if warn {
+                        warning(format!("skipping `{}`: {}", root.display(), e.get_desc()));
+                    }
+                }
+            }
+        }
+
+        ret
+    }
+
+    /**
+     * Reads every manifest that exists. Those that cannot be read are
+     * skipped, with a warning naming them if `warn` is set; `get` runs after
+     * `list` and so leaves the warnings to it.
+     */
+    fn read_packages(&self, warn: bool) -> Vec<Package> {
+        self.manifests(warn).iter().filter_map(|manifest| {
+            if !manifest.exists() {
+                debug!("no manifest; path={}", manifest.display());
+                return None;
+            }
+
+            match PathSource::read_package(manifest) {
+                Ok(pkg) => Some(pkg),
+                // A manifest that only lists the members of a workspace
+                Err(_) if is_workspace(manifest) => None,
+                Err(e) => {
+                    debug!("failed to read manifest; path={}; err={}", manifest.display(), e);
+
+                    if warn {
+                        warning(format!("skipping the manifest at `{}`: {}", manifest.display(), e.get_desc()));
+                    }
+
+                    None
+                }
+            }
+        }).collect()
+    }
 }
 
 impl Show for PathSource {
@@ -40,15 +119,7 @@ impl Source for PathSource {
     fn update(&self) -> CargoResult<()> { Ok(()) }
 
     fn list(&self) -> CargoResult<Vec<Summary>> {
-        Ok(self.paths.iter().filter_map(|path| {
-            match PathSource::read_package(&path.join("Cargo.toml")) {
-                Ok(ref pkg) => Some(pkg.get_summary().clone()),
-                Err(e) => {
-                    debug!("failed to read manifest; path={}; err={}", path.display(), e);
-                    None
-                }
-            }
-        }).collect())
+        Ok(self.read_packages(true).iter().map(|pkg| pkg.get_summary().clone()).collect())
     }
 
     fn download(&self, _: &[PackageId])  -> CargoResult<()>{
@@ -58,28 +129,30 @@ impl Source for PathSource {
     fn get(&self, ids: &[PackageId]) -> CargoResult<Vec<Package>> {
         log!(5, "getting packages; ids={}", ids);
 
-        Ok(self.paths.iter().filter_map(|path| {
-            match PathSource::read_package(&path.join("Cargo.toml")) {
-                Ok(pkg) => {
-                    log!(5, "comparing; pkg={}", pkg);
-
-                    if ids.iter().any(|pkg_id| pkg.get_package_id() == pkg_id) {
-                        Some(pkg)
-                    } else {
-                        None
-                    }
-                }
-                Err(_) => None
-            }
+        Ok(self.read_packages(false).move_iter().filter(|pkg| {
+            log!(5, "comparing; pkg={}", pkg);
+            ids.iter().any(|pkg_id| pkg.get_package_id() == pkg_id)
         }).collect())
     }
 }
 
+fn is_workspace(manifest: &Path) -> bool {
+    match workspace_members(manifest) {
+        Ok(Some(_)) => !is_package_manifest(manifest).unwrap_or(true),
+        _ => false
+    }
+}
+
 fn display(paths: &[Path]) -> Vec<String> {
     paths.iter().map(|p| p.display().to_str()).collect()
 }
 
-fn namespace(path: &Path) -> CargoResult<url::Url> {
+/**
+ * The namespace of packages read from `path` is the `file://` URL of its
+ * real path, so that the same directory reached through different symlinks
+ * yields the same PackageId.
+ */
+pub fn namespace(path: &Path) -> CargoResult<url::Url> {
     let real = try!(realpath(path).map_err(io_error));
     url::from_str(format!("file://{}", real.display()).as_slice()).map_err(|err|
         simple_human(err.as_slice()))
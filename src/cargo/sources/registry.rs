@@ -0,0 +1,292 @@
+use std::fmt;
+use std::collections::HashMap;
+use std::fmt::{Show,Formatter};
+use std::io::{fs,File,UserDir};
+use std::str;
+use semver;
+use serialize::{Decodable,json};
+use url::Url;
+use core::{Dependency,Package,PackageId,Summary};
+use core::source::Source;
+use ops;
+use util::{CargoResult,Require,io_error,human_error,simple_human,process};
+
+/**
+ * A RegistrySource provides packages published to a registry. A registry
+ * is a directory, served locally or over HTTP, laid out as:
+ *
+ *   index                                  one JSON document per line, for
+ *                                          every version of every package
+ *   packages/<name>/<name>-<vers>.tar.gz   the package sources
+ *
+ * Each line of the index looks like:
+ *
+ *   {"name":"foo","vers":"0.1.0","deps":[{"name":"bar","req":"0.2.0"}],"cksum":"<sha256>"}
+ *
+ * Dependencies may also give `optional`, `features` and `default_features`,
+ * and packages their `features`, as in the manifest.
+ *
+ * Each tarball contains a single `<name>-<vers>` directory with the
+ * package's `Cargo.toml` at its root.
+ *
+ * Locally, the index, the downloaded tarballs and their unpacked sources
+ * live under the directory the source is created with.
+ */
+pub struct RegistrySource {
+    url: Url,
+    index_path: Path,
+    cache_path: Path,
+    src_path: Path
+}
+
+#[deriving(Decodable,Encodable,Clone,Show)]
+pub struct RegistryPackage {
+    pub name: String,
+    pub vers: String,
+    pub deps: Vec<RegistryDependency>,
+    pub features: Option<HashMap<String, Vec<String>>>,
+    pub cksum: String
+}
+
+#[deriving(Decodable,Encodable,Clone,Show)]
+pub struct RegistryDependency {
+    pub name: String,
+    pub req: String,
+    pub optional: Option<bool>,
+    pub features: Option<Vec<String>>,
+    pub default_features: Option<bool>
+}
+
+impl RegistrySource {
+    pub fn new(url: Url, root: Path) -> RegistrySource {
+        RegistrySource {
+            url: url,
+            index_path: root.join("index"),
+            cache_path: root.join("cache"),
+            src_path: root.join("src")
+        }
+    }
+
+    pub fn get_url<'a>(&'a self) -> &'a Url {
+        &self.url
+    }
+
+    /**
+     * Reads every entry of the locally synced index.
+     */
+    fn index(&self) -> CargoResult<Vec<RegistryPackage>> {
+        let mut file = try!(File::open(&self.index_path).map_err(|err|
+            human_error(format!("Couldn't read the index of the registry at `{}`", self.url), format!("path={}", self.index_path.display()), io_error(err))));
+
+        let contents = try!(file.read_to_str().map_err(io_error));
+        let mut ret = Vec::new();
+
+        for line in contents.as_slice().lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let json = try!(json::from_str(line).map_err(|_|
+                simple_human(format!("The index of the registry at `{}` contains invalid JSON: {}", self.url, line))));
+
+            let mut decoder = json::Decoder::new(json);
+            let pkg: RegistryPackage = try!(Decodable::decode(&mut decoder).map_err(|_|
+                simple_human(format!("The index of the registry at `{}` contains an invalid entry: {}", self.url, line))));
+
+            ret.push(pkg);
+        }
+
+        Ok(ret)
+    }
+
+    fn to_summary(&self, pkg: &RegistryPackage) -> CargoResult<Summary> {
+        try!(semver::parse(pkg.vers.as_slice()).require(
+            simple_human(format!("`{}` has an invalid version `{}` in the registry index", pkg.name, pkg.vers))));
+
+        let mut deps = Vec::new();
+
+        for dep in pkg.deps.iter() {
+            deps.push(try!(Dependency::parse(dep.name.as_slice(), dep.req.as_slice()))
+                .optional(dep.optional.unwrap_or(false))
+                .features(dep.features.clone().unwrap_or_else(|| Vec::new()))
+                .default_features(dep.default_features.unwrap_or(true)));
+        }
+
+        let features = pkg.features.clone().unwrap_or_else(|| HashMap::new());
+
+        Ok(Summary::new(&PackageId::new(pkg.name.as_slice(), pkg.vers.as_slice(), &self.url), deps.as_slice()).features(features))
+    }
+
+    /**
+     * The package ids, out of `ids`, that this registry provides.
+     */
+    fn ours<'a>(&self, ids: &'a [PackageId]) -> Vec<&'a PackageId> {
+        ids.iter().filter(|id| *id.get_namespace() == self.url).collect()
+    }
+
+    /**
+     * Copies the file at `path`, relative to the root of the registry, to
+     * `dest`. Registries on the local file system are copied directly; any
+     * other URL is downloaded with curl.
+     */
+    fn fetch(&self, path: &str, dest: &Path) -> CargoResult<()> {
+        try!(fs::mkdir_recursive(&dest.dir_path(), UserDir).map_err(|err|
+            human_error(format!("Couldn't create `{}`", dest.dir_path().display()), None::<&str>, io_error(err))));
+
+        match self.url.scheme.as_slice() {
+            "file" => {
+                let src = Path::new(self.url.path.as_slice()).join(path);
+
+                fs::copy(&src, dest).map_err(|err|
+                    human_error(format!("Couldn't copy `{}` from the registry", src.display()), None::<&str>, io_error(err)))
+            },
+            _ => {
+                let url = format!("{}/{}", self.url.to_str().as_slice().trim_right_chars('/'), path);
+
+                process("curl")
+                    .args(["-sSfL".to_str(), "-o".to_str(), dest.display().to_str(), url.clone()])
+                    .exec_with_output()
+                    .map(|_| ())
+                    .map_err(|err| human_error(format!("Couldn't download `{}`", url), None::<&str>, err))
+            }
+        }
+    }
+
+    fn tarball_path(&self, id: &PackageId) -> Path {
+        self.cache_path.join(format!("{}-{}.tar.gz", id.get_name(), id.get_version()))
+    }
+
+    fn unpacked_path(&self, id: &PackageId) -> Path {
+        self.src_path.join(format!("{}-{}", id.get_name(), id.get_version()))
+    }
+}
+
+impl Show for RegistrySource {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "registry at {}", self.url)
+    }
+}
+
+impl Source for RegistrySource {
+    fn update(&self) -> CargoResult<()> {
+        println!("Updating registry `{}`", self.url);
+        self.fetch("index", &self.index_path)
+    }
+
+    fn list(&self) -> CargoResult<Vec<Summary>> {
+        let index = try!(self.index());
+        let mut ret = Vec::new();
+
+        for pkg in index.iter() {
+            ret.push(try!(self.to_summary(pkg)));
+        }
+
+        Ok(ret)
+    }
+
+    fn download(&self, packages: &[PackageId]) -> CargoResult<()> {
+        let index = try!(self.index());
+
+        for id in self.ours(packages).iter() {
+            let tarball = self.tarball_path(*id);
+
+            // Tarballs are only moved into the cache once they have been
+            // verified, so an interrupted download is never trusted
+            if tarball.exists() {
+                continue;
+            }
+
+            let version = id.get_version().to_str();
+            let pkg = try!(index.iter().find(|pkg| pkg.name.as_slice() == id.get_name() && pkg.vers == version).require(
+                simple_human(format!("{} is not in the registry index", id))));
+
+            println!("Downloading {}", id);
+
+            let path = format!("packages/{}/{}-{}.tar.gz", pkg.name, pkg.name, pkg.vers);
+            let partial = partial_path(&tarball);
+            try!(self.fetch(path.as_slice(), &partial));
+
+            let actual = try!(sha256(&partial));
+
+            if actual != pkg.cksum {
+                let _ = fs::unlink(&partial);
+                return Err(simple_human(format!("Checksum mismatch for {}; expected {}, but the download was {}", id, pkg.cksum, actual)));
+            }
+
+            try!(fs::rename(&partial, &tarball).map_err(|err|
+                human_error(format!("Couldn't move the download of {} into `{}`", id, tarball.display()), None::<&str>, io_error(err))));
+        }
+
+        Ok(())
+    }
+
+    fn get(&self, packages: &[PackageId]) -> CargoResult<Vec<Package>> {
+        let mut ret = Vec::new();
+
+        for id in self.ours(packages).iter() {
+            let dest = self.unpacked_path(*id);
+
+            // Like tarballs, sources are unpacked elsewhere and only moved
+            // into place once the whole tarball was unpacked
+            if !dest.exists() {
+                let partial = partial_path(&dest);
+
+                if partial.exists() {
+                    try!(fs::rmdir_recursive(&partial).map_err(io_error));
+                }
+
+                try!(fs::mkdir_recursive(&partial, UserDir).map_err(io_error));
+
+                let tarball = self.tarball_path(*id);
+                try!(process("tar")
+                     .args(["-xzf".to_str(), tarball.display().to_str(), "-C".to_str(), partial.display().to_str()])
+                     .exec_with_output()
+                     .map_err(|err| human_error(format!("Couldn't unpack {}", id), format!("path={}", tarball.display()), err)));
+
+                let unpacked = partial.join(dest.filename_str().unwrap_or(""));
+                try!(fs::rename(&unpacked, &dest).map_err(|err|
+                    human_error(format!("Couldn't unpack {}; the tarball has no `{}` directory", id, dest.filename_str().unwrap_or("")), format!("path={}", tarball.display()), io_error(err))));
+                try!(fs::rmdir_recursive(&partial).map_err(io_error));
+            }
+
+            ret.push(try!(ops::read_package(&dest.join("Cargo.toml"), &self.url)));
+        }
+
+        Ok(ret)
+    }
+}
+
+/**
+ * Where a download or an unpacked tarball is put until it is complete.
+ */
+fn partial_path(path: &Path) -> Path {
+    Path::new(format!("{}.part", path.display()))
+}
+
+/**
+ * Returns the hex encoded SHA-256 digest of the file at `path`, computed by
+ * `sha256sum`, or `shasum` where coreutils are not installed.
+ */
+pub fn sha256(path: &Path) -> CargoResult<String> {
+    for &(tool, flags) in [("sha256sum", ""), ("shasum", "-a 256")].iter() {
+        let mut args: Vec<String> = flags.words().map(|word| word.to_str()).collect();
+        args.push(path.display().to_str());
+
+        let output = match process(tool).args(args.as_slice()).exec_with_output() {
+            Ok(output) => output,
+            Err(err) => {
+                log!(5, "couldn't compute the checksum; tool={}; err={}", tool, err);
+                continue;
+            }
+        };
+
+        let out = str::from_utf8_lossy(output.output.as_slice()).to_str();
+
+        match out.as_slice().words().next() {
+            Some(digest) => return Ok(digest.to_str()),
+            None => ()
+        }
+    }
+
+    Err(simple_human(format!("Couldn't compute the checksum of `{}`; neither sha256sum nor shasum could be run", path.display())))
+}
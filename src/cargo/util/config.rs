@@ -2,7 +2,7 @@ use std::{io,fmt};
 use std::collections::HashMap;
 use serialize::{Encodable,Encoder};
 use toml;
-use util::{other_error,CargoResult,Require};
+use util::{other_error,simple_human,CargoResult,Require};
 
 #[deriving(Eq,PartialEq,Clone,Encodable,Decodable)]
 pub enum Location {
@@ -78,6 +78,22 @@ pub fn get_config(pwd: Path, key: &str) -> CargoResult<ConfigValue> {
         .map_err(|_| other_error("config key not found").with_detail(format!("key={}", key)))
 }
 
+/**
+ * The value of a config key that has to be a String, or `None` if no
+ * config file sets it.
+ */
+pub fn get_string(pwd: Path, key: &str) -> CargoResult<Option<String>> {
+    let value = match get_config(pwd, key) {
+        Ok(value) => value,
+        Err(_) => return Ok(None)
+    };
+
+    match value.get_value() {
+        &String(ref string) => Ok(Some(string.clone())),
+        &List(_) => Err(simple_human(format!("The `{}` key in .cargo/config should be a String, but it was a List", key)))
+    }
+}
+
 pub fn all_configs(pwd: Path) -> CargoResult<HashMap<String, ConfigValue>> {
     let mut map = HashMap::new();
 
@@ -1,4 +1,6 @@
-use util::{other_error,CargoResult,CargoError};
+use std::io::File;
+use util::{other_error,io_error,human_error,CargoResult,CargoError};
+use util::toml::{to_workspace_members,has_project};
 
 pub fn find_project(pwd: Path, file: &str) -> CargoResult<Path> {
     let mut current = pwd.clone();
@@ -14,6 +16,60 @@ pub fn find_project(pwd: Path, file: &str) -> CargoResult<Path> {
     Err(manifest_missing_err(&pwd, file.as_slice()))
 }
 
+/**
+ * Finds the root of the workspace the project in `dir` belongs to: the
+ * closest directory at or above it whose Cargo.toml has a `[workspace]`
+ * section. Its root is in the workspace itself; any other project only if
+ * it is listed as a member. A Cargo.toml that cannot be read does not
+ * define a workspace, since it may belong to something else entirely.
+ */
+pub fn find_workspace_root(dir: &Path) -> CargoResult<Option<Path>> {
+    let mut current = dir.clone();
+
+    loop {
+        let manifest = current.join("Cargo.toml");
+
+        match workspace_members(&manifest) {
+            Ok(Some(members)) => {
+                let is_member = current == *dir || members.iter().any(|member| current.join(member.as_slice()) == *dir);
+                return Ok(if is_member { Some(current) } else { None });
+            },
+            Ok(None) => (),
+            Err(err) => debug!("not a workspace; manifest={}; err={}", manifest.display(), err)
+        }
+
+        if !current.pop() { break; }
+    }
+
+    Ok(None)
+}
+
+/**
+ * The members listed in the manifest at `path`, or None if it does not
+ * exist or does not define a workspace.
+ */
+pub fn workspace_members(path: &Path) -> CargoResult<Option<Vec<String>>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let contents = try!(File::open(path).read_to_end().map_err(io_error));
+
+    to_workspace_members(contents.as_slice()).map_err(|err|
+        human_error(format!("Could not read `{}`", path.display()), "", err))
+}
+
+/**
+ * Whether the manifest at `path` describes a package rather than only
+ * listing the members of a workspace.
+ */
+pub fn is_package_manifest(path: &Path) -> CargoResult<bool> {
+    let contents = try!(File::open(path).read_to_end().map_err(io_error));
+
+    has_project(contents.as_slice()).map_err(|err|
+        human_error(format!("Could not read `{}`", path.display()), "", err))
+}
+
 fn manifest_missing_err(pwd: &Path, file: &str) -> CargoError {
     other_error("manifest not found")
         .with_detail(format!("pwd={}; file={}", pwd.display(), file))
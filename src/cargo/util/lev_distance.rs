@@ -0,0 +1,43 @@
+use std::cmp;
+
+/**
+ * The Levenshtein distance between two strings: the number of single
+ * character insertions, deletions and substitutions needed to turn one
+ * into the other.
+ */
+pub fn lev_distance(a: &str, b: &str) -> uint {
+    let b_chars: Vec<char> = b.chars().collect();
+
+    // The distances from the current prefix of `a` to each prefix of `b`
+    let mut prev: Vec<uint> = range(0, b_chars.len() + 1).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut curr = vec!(i + 1);
+
+        for (j, cb) in b_chars.iter().enumerate() {
+            let substitution = *prev.get(j) + if ca == *cb { 0 } else { 1 };
+            let insertion = *curr.get(j) + 1;
+            let deletion = *prev.get(j + 1) + 1;
+
+            curr.push(cmp::min(substitution, cmp::min(insertion, deletion)));
+        }
+
+        prev = curr;
+    }
+
+    *prev.last().unwrap()
+}
+
+#[cfg(test)]
+mod test {
+    use super::lev_distance;
+
+    #[test]
+    pub fn test_lev_distance() {
+        assert_eq!(lev_distance("compile", "compile"), 0);
+        assert_eq!(lev_distance("compil", "compile"), 1);
+        assert_eq!(lev_distance("comiple", "compile"), 2);
+        assert_eq!(lev_distance("", "test"), 4);
+        assert_eq!(lev_distance("kitten", "sitting"), 3);
+    }
+}
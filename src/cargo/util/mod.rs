@@ -1,6 +1,7 @@
 pub use self::process_builder::{process,ProcessBuilder};
-pub use self::result::{CargoError,CargoResult,Wrap,Require,ToCLI,other_error,human_error,simple_human,toml_error,io_error,process_error};
+pub use self::result::{CargoError,CargoResult,Wrap,Require,ToCLI,other_error,human_error,simple_human,toml_error,io_error,process_error,warning};
 pub use self::paths::realpath;
+pub use self::lev_distance::lev_distance;
 
 pub mod graph;
 pub mod process_builder;
@@ -9,3 +10,5 @@ pub mod important_paths;
 pub mod result;
 pub mod toml;
 pub mod paths;
+pub mod lev_distance;
+pub mod usage;
@@ -69,6 +69,7 @@ impl ProcessBuilder {
         let mut command = self.build_command();
         command
             .env(self.build_env().as_slice())
+            .stdin(InheritFd(0))
             .stdout(InheritFd(1))
             .stderr(InheritFd(2));
 
@@ -69,6 +69,13 @@ pub fn toml_error(desc: &'static str, error: toml::Error) -> CargoError {
     }
 }
 
+/**
+ * Reports a problem that does not stop the command, on stderr.
+ */
+pub fn warning<T: Show>(msg: T) {
+    let _ = writeln!(&mut io::stderr(), "warning: {}", msg);
+}
+
 #[deriving(PartialEq,Clone)]
 pub struct CargoError {
     pub kind: CargoErrorKind,
@@ -116,6 +123,10 @@ impl CargoError {
             CargoError { kind: HumanReadableError, desc: BoxedDescription(desc), detail: detail, .. } => {
                 CLIError::new(desc, detail, exit_code)
             },
+            // A human readable error wrapped with what was being done
+            CargoError { kind: HumanReadableError, desc: StaticDescription(desc), cause: Some(cause), .. } => {
+                CLIError::new(format!("{}: {}", desc, cause.get_desc()), cause.detail, exit_code)
+            },
             ref err @ CargoError { kind: InternalError, desc: StaticDescription(desc), detail: None, .. } => {
                 CLIError::new(format!("An unexpected error occurred: {}", err), Some(desc), exit_code)
             },
@@ -1,22 +1,77 @@
 use toml;
+use semver;
 use url;
 use url::Url;
 use std::collections::HashMap;
 use serialize::Decodable;
 
-use core::source::{SourceId,GitKind};
-use core::manifest::{LibKind,Lib};
+use core::source::{SourceId,GitKind,PathKind};
+use sources;
+use sources::git::{GitReference,Master,Tag,Rev};
+use core::manifest::{LibKind,Lib,Profile};
 use core::{Summary,Manifest,Target,Dependency,PackageId};
+use core::dependency::{DependencyKind,Normal,Development};
 use util::{CargoResult,Require,simple_human,toml_error};
+use util::result::HumanReadableError;
 
-pub fn to_manifest(contents: &[u8], namespace: &Url) -> CargoResult<Manifest> {
+pub fn to_manifest(contents: &[u8], namespace: &Url, root: &Path) -> CargoResult<Manifest> {
+    let value = try!(toml::parse_from_bytes(contents).map_err(|_|
+        simple_human("Cargo.toml is not valid Toml")));
+
+    // Errors that already describe the problem are shown as they are
+    let toml = try!(toml_to_manifest(value).map_err(|err| match err.kind {
+        HumanReadableError => err,
+        _ => simple_human("Cargo.toml is not a valid Cargo manifest")
+    }));
+
+    toml.to_manifest(namespace, root)
+}
+
+/**
+ * Whether a manifest describes a package, i.e. has a `[project]` section,
+ * rather than only listing the members of a workspace.
+ */
+pub fn has_project(contents: &[u8]) -> CargoResult<bool> {
     let root = try!(toml::parse_from_bytes(contents).map_err(|_|
         simple_human("Cargo.toml is not valid Toml")));
 
-    let toml = try!(toml_to_manifest(root).map_err(|_|
-        simple_human("Cargo.toml is not a valid Cargo manifest")));
+    Ok(root.lookup("project").is_some())
+}
+
+/**
+ * The member paths listed in the `[workspace]` section of a manifest, if it
+ * has one:
+ *
+ *   [workspace]
+ *   members = ["core", "cli"]
+ */
+pub fn to_workspace_members(contents: &[u8]) -> CargoResult<Option<Vec<String>>> {
+    let root = try!(toml::parse_from_bytes(contents).map_err(|_|
+        simple_human("Cargo.toml is not valid Toml")));
+
+    let workspace = match root.lookup("workspace") {
+        Some(workspace) => workspace,
+        None => return Ok(None)
+    };
+
+    let invalid = || simple_human("`members` in `[workspace]` must be an array of paths");
+
+    match workspace.lookup("members") {
+        Some(&toml::Array(ref members)) => {
+            let mut ret = Vec::new();
 
-    toml.to_manifest(namespace)
+            for member in members.iter() {
+                match *member {
+                    toml::String(ref path) => ret.push(path.clone()),
+                    _ => return Err(invalid())
+                }
+            }
+
+            Ok(Some(ret))
+        },
+        Some(_) => Err(invalid()),
+        None => Ok(Some(Vec::new()))
+    }
 }
 
 fn toml_to_manifest(root: toml::Value) -> CargoResult<TomlManifest> {
@@ -32,11 +87,97 @@ fn toml_to_manifest(root: toml::Value) -> CargoResult<TomlManifest> {
     let lib = decode(&root, "lib").ok();
     let bin = decode(&root, "bin").ok();
 
-    let deps = root.lookup("dependencies");
+    let deps = try!(decode_dependencies(&root, "dependencies"));
+    let dev_deps = try!(decode_dependencies(&root, "dev-dependencies"));
+    let profiles = try!(decode_profiles(&root));
+    let features = try!(decode_features(&root));
+
+    Ok(TomlManifest { project: box project, lib: lib, bin: bin, dependencies: deps, dev_dependencies: dev_deps, profile: profiles, features: features })
+}
+
+fn decode_features(root: &toml::Value) -> CargoResult<Option<HashMap<String, Vec<String>>>> {
+    let features = match root.lookup("features") {
+        Some(features) => try!(features.get_table().require(simple_human("features must be a table"))),
+        None => return Ok(None)
+    };
+
+    let mut ret = HashMap::new();
+
+    for (name, enabled) in features.iter() {
+        let invalid = || simple_human(format!("Feature `{}` must be an array of feature and dependency names", name));
+        let mut names = Vec::new();
+
+        match *enabled {
+            toml::Array(ref enabled) => {
+                for value in enabled.iter() {
+                    names.push(try!(value.get_str().require(invalid())).clone());
+                }
+            },
+            _ => return Err(invalid())
+        }
+
+        ret.insert(name.clone(), names);
+    }
+
+    Ok(Some(ret))
+}
+
+fn decode_profiles(root: &toml::Value) -> CargoResult<Option<HashMap<String, TomlProfile>>> {
+    let profiles = match root.lookup("profile") {
+        Some(profiles) => try!(profiles.get_table().require(simple_human("profile must be a table"))),
+        None => return Ok(None)
+    };
+
+    let mut ret = HashMap::new();
+
+    for (name, profile) in profiles.iter() {
+        let invalid = |key: &str, kind: &str| {
+            simple_human(format!("`{}` in [profile.{}] must be {}", key, name, kind))
+        };
+
+        try!(profile.get_table().require(simple_human(format!("profile.{} must be a table", name))));
+
+        let opt_level = match profile.lookup("opt-level") {
+            Some(&toml::PosInt(level)) => Some(level as uint),
+            Some(_) => return Err(invalid("opt-level", "an integer")),
+            None => None
+        };
+
+        let debug = match profile.lookup("debug") {
+            Some(&toml::Boolean(debug)) => Some(debug),
+            Some(_) => return Err(invalid("debug", "true or false")),
+            None => None
+        };
+
+        let rustc_args = match profile.lookup("rustc-args") {
+            Some(&toml::Array(ref args)) => {
+                let mut ret = Vec::new();
+
+                for arg in args.iter() {
+                    match *arg {
+                        toml::String(ref arg) => ret.push(arg.clone()),
+                        _ => return Err(invalid("rustc-args", "an array of strings"))
+                    }
+                }
+
+                Some(ret)
+            },
+            Some(_) => return Err(invalid("rustc-args", "an array of strings")),
+            None => None
+        };
+
+        ret.insert(name.clone(), TomlProfile { opt_level: opt_level, debug: debug, rustc_args: rustc_args });
+    }
+
+    Ok(Some(ret))
+}
+
+fn decode_dependencies(root: &toml::Value, key: &str) -> CargoResult<Option<HashMap<String, TomlDependency>>> {
+    let deps = root.lookup(key);
 
-    let deps = match deps {
+    match deps {
         Some(deps) => {
-            let table = try!(deps.get_table().require(simple_human("dependencies must be a table"))).clone();
+            let table = try!(deps.get_table().require(simple_human(format!("{} must be a table", key)))).clone();
 
             let mut deps: HashMap<String, TomlDependency> = HashMap::new();
 
@@ -45,12 +186,28 @@ fn toml_to_manifest(root: toml::Value) -> CargoResult<TomlManifest> {
                     &toml::String(ref string) => { deps.insert(k.clone(), SimpleDep(string.clone())); },
                     &toml::Table(ref table) => {
                         let mut details = HashMap::<String, String>::new();
-
-                        for (k, v) in table.iter() {
-                            let v = try!(v.get_str()
-                                         .require(simple_human("dependency values must be string")));
-
-                            details.insert(k.clone(), v.clone());
+                        let mut optional = false;
+                        let mut features = Vec::new();
+                        let mut default_features = true;
+
+                        for (key, v) in table.iter() {
+                            let invalid = || simple_human(format!("`{}` in dependency `{}` must be {}", key, k, match key.as_slice() {
+                                "optional" | "default-features" => "true or false",
+                                "features" => "an array of strings",
+                                _ => "a string"
+                            }));
+
+                            match (key.as_slice(), v) {
+                                ("optional", &toml::Boolean(value)) => optional = value,
+                                ("default-features", &toml::Boolean(value)) => default_features = value,
+                                ("features", &toml::Array(ref values)) => {
+                                    for feature in values.iter() {
+                                        features.push(try!(feature.get_str().require(invalid())).clone());
+                                    }
+                                },
+                                ("optional", _) | ("default-features", _) | ("features", _) => return Err(invalid()),
+                                (_, value) => { details.insert(key.clone(), try!(value.get_str().require(invalid())).clone()); }
+                            }
                         }
 
                         let version = try!(details.find_equiv(&"version")
@@ -58,6 +215,9 @@ fn toml_to_manifest(root: toml::Value) -> CargoResult<TomlManifest> {
 
                         deps.insert(k.clone(), DetailedDep(DetailedTomlDependency {
                             version: version,
+                            optional: optional,
+                            features: features,
+                            default_features: default_features,
                             other: details
                         }));
                     },
@@ -65,12 +225,10 @@ fn toml_to_manifest(root: toml::Value) -> CargoResult<TomlManifest> {
                 }
             }
 
-            Some(deps)
+            Ok(Some(deps))
         },
-        None => None
-    };
-
-    Ok(TomlManifest { project: box project, lib: lib, bin: bin, dependencies: deps })
+        None => Ok(None)
+    }
 }
 
 type TomlLibTarget = TomlTarget;
@@ -89,33 +247,100 @@ pub enum TomlDependency {
 #[deriving(Encodable,PartialEq,Clone,Show)]
 pub struct DetailedTomlDependency {
     version: String,
+    optional: bool,
+    features: Vec<String>,
+    default_features: bool,
     other: HashMap<String, String>
 }
 
+impl DetailedTomlDependency {
+    /**
+     * Builds the SourceId for a dependency that names its own source. Git
+     * dependencies may pin one of `branch`, `tag` or `rev`, and default to
+     * the `master` branch. Path dependencies are relative to `root`, the
+     * directory containing the manifest.
+     */
+    fn to_source_id(&self, name: &str, root: &Path) -> CargoResult<Option<SourceId>> {
+        match (self.other.find_equiv(&"path"), self.other.find_equiv(&"git")) {
+            (Some(_), Some(_)) => {
+                return Err(simple_human(format!("Dependency `{}` specifies both a `path` and a `git` source; only one may be given", name)));
+            },
+            (Some(path), None) => {
+                let url = try!(sources::path::namespace(&root.join(path.as_slice())));
+                return Ok(Some(SourceId::new(PathKind, url)));
+            },
+            _ => ()
+        }
+
+        let branch = self.other.find_equiv(&"branch");
+        let tag = self.other.find_equiv(&"tag");
+        let rev = self.other.find_equiv(&"rev");
+
+        let git = match self.other.find_equiv(&"git") {
+            Some(git) => git,
+            None => {
+                if branch.is_some() || tag.is_some() || rev.is_some() {
+                    return Err(simple_human(format!("Dependency `{}` specifies a `branch`, `tag` or `rev` but is not a git dependency", name)));
+                }
+
+                return Ok(None);
+            }
+        };
+
+        let reference = match (branch, tag, rev) {
+            (None, None, None) => Master,
+            (Some(branch), None, None) => GitReference::branch(branch.as_slice()),
+            (None, Some(tag), None) => Tag(tag.clone()),
+            (None, None, Some(rev)) => Rev(rev.clone()),
+            _ => return Err(simple_human(format!("Dependency `{}` specifies more than one of `branch`, `tag` and `rev`; only one may be given", name)))
+        };
+
+        let url = try!(url::from_str(git.as_slice()).map_err(|_|
+            simple_human(format!("Dependency `{}` has an invalid git URL `{}`", name, git))));
+
+        Ok(Some(SourceId::new(GitKind(reference), url)))
+    }
+}
+
 #[deriving(Encodable,PartialEq,Clone)]
 pub struct TomlManifest {
     project: Box<TomlProject>,
     lib: Option<Vec<TomlLibTarget>>,
     bin: Option<Vec<TomlBinTarget>>,
     dependencies: Option<HashMap<String, TomlDependency>>,
+    dev_dependencies: Option<HashMap<String, TomlDependency>>,
+    profile: Option<HashMap<String, TomlProfile>>,
+    features: Option<HashMap<String, Vec<String>>>
+}
+
+#[deriving(Encodable,PartialEq,Clone,Show)]
+pub struct TomlProfile {
+    opt_level: Option<uint>,
+    debug: Option<bool>,
+    rustc_args: Option<Vec<String>>
 }
 
 #[deriving(Decodable,Encodable,PartialEq,Clone,Show)]
 pub struct TomlProject {
     pub name: String,
     pub version: String,
-    pub authors: Vec<String>
+    pub authors: Option<Vec<String>>,
+    pub build: Option<String>
 }
 
 impl TomlProject {
-    pub fn to_package_id(&self, namespace: &Url) -> PackageId {
-        PackageId::new(self.name.as_slice(), self.version.as_slice(), namespace)
+    pub fn to_package_id(&self, namespace: &Url) -> CargoResult<PackageId> {
+        try!(semver::parse(self.version.as_slice()).require(
+            simple_human(format!("`{}` is not a valid version for package `{}`", self.version, self.name))));
+
+        Ok(PackageId::new(self.name.as_slice(), self.version.as_slice(), namespace))
     }
 }
 
 impl TomlManifest {
-    pub fn to_manifest(&self, namespace: &Url) -> CargoResult<Manifest> {
+    pub fn to_manifest(&self, namespace: &Url, root: &Path) -> CargoResult<Manifest> {
         let mut sources = vec!();
+        let mut dev_sources = vec!();
 
         // Get targets
         let targets = normalize(self.lib.as_ref().map(|l| l.as_slice()), self.bin.as_ref().map(|b| b.as_slice()));
@@ -127,41 +352,126 @@ impl TomlManifest {
         let mut deps = Vec::new();
 
         // Collect the deps
-        match self.dependencies {
-            Some(ref dependencies) => {
-                for (n, v) in dependencies.iter() {
-                    let version = match *v {
-                        SimpleDep(ref string) => string.clone(),
-                        DetailedDep(ref details) => {
-                            details.other.find_equiv(&"git").map(|git| {
-                                // TODO: Don't unwrap here
-                                let kind = GitKind("master".to_str());
-                                let url = url::from_str(git.as_slice()).unwrap();
-                                sources.push(SourceId::new(kind, url));
-                            });
-                            details.version.clone()
-                        }
-                    };
+        try!(process_dependencies(&mut deps, &mut sources, self.dependencies.as_ref(), Normal, root));
+        try!(process_dependencies(&mut deps, &mut dev_sources, self.dev_dependencies.as_ref(), Development, root));
 
-                    deps.push(try!(Dependency::parse(n.as_slice(), version.as_slice())))
-                }
-            }
-            None => ()
-        }
+        let features = self.features.clone().unwrap_or_else(|| HashMap::new());
+        try!(validate_features(&features, deps.as_slice()));
+
+        let authors = self.project.authors.clone().unwrap_or_else(|| Vec::new());
+        let profiles = try!(self.profiles());
 
         Ok(Manifest::new(
-                &Summary::new(&self.project.to_package_id(namespace), deps.as_slice()),
+                &Summary::new(&try!(self.project.to_package_id(namespace)), deps.as_slice()).features(features),
+                authors.as_slice(),
                 targets.as_slice(),
                 &Path::new("target"),
-                sources))
+                sources,
+                profiles,
+                self.project.build.clone()).dev_sources(dev_sources))
     }
+
+    /**
+     * The default profiles, with the settings from `[profile.*]` applied.
+     */
+    fn profiles(&self) -> CargoResult<Vec<Profile>> {
+        let mut profiles = Profile::default_profiles();
+
+        let overrides = match self.profile {
+            Some(ref overrides) => overrides,
+            None => return Ok(profiles)
+        };
+
+        for (name, toml) in overrides.iter() {
+            let profile = try!(profiles.mut_iter().find(|profile| profile.get_name() == name.as_slice()).require(
+                simple_human(format!("Unknown profile `{}`; the profiles are `dev`, `release` and `test`", name))));
+
+            let mut updated = profile.clone();
+
+            match toml.opt_level {
+                Some(level) => updated = updated.opt_level(level),
+                None => ()
+            }
+
+            match toml.debug {
+                Some(debug) => updated = updated.debug(debug),
+                None => ()
+            }
+
+            match toml.rustc_args {
+                Some(ref args) => updated = updated.rustc_args(args.clone()),
+                None => ()
+            }
+
+            *profile = updated;
+        }
+
+        Ok(profiles)
+    }
+}
+
+fn process_dependencies(deps: &mut Vec<Dependency>, sources: &mut Vec<SourceId>,
+                        new_deps: Option<&HashMap<String, TomlDependency>>,
+                        kind: DependencyKind, root: &Path) -> CargoResult<()> {
+    let new_deps = match new_deps {
+        Some(new_deps) => new_deps,
+        None => return Ok(())
+    };
+
+    for (n, v) in new_deps.iter() {
+        let dep = match *v {
+            SimpleDep(ref string) => try!(Dependency::parse(n.as_slice(), string.as_slice())),
+            DetailedDep(ref details) => {
+                match try!(details.to_source_id(n.as_slice(), root)) {
+                    Some(source_id) => sources.push(source_id),
+                    None => ()
+                }
+
+                if details.optional && kind == Development {
+                    return Err(simple_human(format!("Development dependency `{}` cannot be optional", n)));
+                }
+
+                try!(Dependency::parse(n.as_slice(), details.version.as_slice()))
+                    .optional(details.optional)
+                    .features(details.features.clone())
+                    .default_features(details.default_features)
+            }
+        };
+
+        deps.push(dep.kind(kind));
+    }
+
+    Ok(())
+}
+
+/**
+ * Every feature has to enable only other features and optional
+ * dependencies, and may not share its name with a dependency.
+ */
+fn validate_features(features: &HashMap<String, Vec<String>>, deps: &[Dependency]) -> CargoResult<()> {
+    for (name, enabled) in features.iter() {
+        if deps.iter().any(|dep| dep.get_name() == name.as_slice()) {
+            return Err(simple_human(format!("Feature `{}` has the same name as a dependency", name)));
+        }
+
+        for member in enabled.iter() {
+            let is_optional_dep = deps.iter().any(|dep| dep.is_optional() && dep.get_name() == member.as_slice());
+
+            if !features.contains_key(member) && !is_optional_dep {
+                return Err(simple_human(format!("Feature `{}` enables `{}`, which is neither a feature nor an optional dependency", name, member)));
+            }
+        }
+    }
+
+    Ok(())
 }
 
 #[deriving(Decodable,Encodable,PartialEq,Clone,Show)]
 struct TomlTarget {
     name: String,
     crate_type: Option<Vec<String>>,
-    path: Option<String>
+    path: Option<String>,
+    plugin: Option<bool>
 }
 
 fn normalize(lib: Option<&[TomlLibTarget]>, bin: Option<&[TomlBinTarget]>) -> Vec<Target> {
@@ -171,7 +481,7 @@ fn normalize(lib: Option<&[TomlLibTarget]>, bin: Option<&[TomlBinTarget]>) -> Ve
         let l = &libs[0];
         let path = l.path.clone().unwrap_or_else(|| format!("src/{}.rs", l.name));
         let crate_types = l.crate_type.clone().and_then(|kinds| LibKind::from_strs(kinds).ok()).unwrap_or_else(|| vec!(Lib));
-        dst.push(Target::lib_target(l.name.as_slice(), crate_types, &Path::new(path)));
+        dst.push(Target::lib_target(l.name.as_slice(), crate_types, &Path::new(path)).plugin(l.plugin.unwrap_or(false)));
     }
 
     fn bin_targets(dst: &mut Vec<Target>, bins: &[TomlBinTarget], default: |&TomlBinTarget| -> String) {
@@ -0,0 +1,342 @@
+use std::fmt;
+use std::fmt::{Show,Formatter};
+use serialize::{Decoder,Decodable};
+use hammer::{FlagConfig,FlagConfiguration};
+
+#[deriving(PartialEq,Clone,Show)]
+pub enum FlagKind {
+    Switch,
+    Required,
+    Optional,
+    Repeated,
+    Rest
+}
+
+#[deriving(PartialEq,Clone,Show)]
+pub struct Flag {
+    name: String,
+    kind: FlagKind,
+    short: Option<char>
+}
+
+impl Flag {
+    /**
+     * The flag as it is passed on the command line. Hammer turns the
+     * underscores of a field name into dashes.
+     */
+    pub fn get_name(&self) -> String {
+        format!("--{}", self.name.replace("_", "-"))
+    }
+
+    pub fn get_kind(&self) -> FlagKind {
+        self.kind
+    }
+
+    /**
+     * The single letter alias declared for the flag in its `FlagConfig`.
+     */
+    pub fn get_short(&self) -> Option<char> {
+        self.short
+    }
+
+    fn describe(&self) -> String {
+        let value = self.name.replace("_", "-");
+        let name = match self.short {
+            Some(short) => format!("-{}, {}", short, self.get_name()),
+            None => self.get_name()
+        };
+
+        match self.kind {
+            Switch => name,
+            Required | Optional => format!("{} <{}>", name, value),
+            Repeated => format!("{} <{}>...", name, value),
+            Rest => "[<args>...]".to_str()
+        }
+    }
+}
+
+/**
+ * The flags a command accepts are declared once, as the struct its
+ * arguments are decoded into. `FlagCollector` walks that declaration with
+ * a decoder that records each field instead of reading a value, so the
+ * `--help` output can never drift from what the command accepts.
+ */
+pub struct FlagCollector {
+    flags: Vec<Flag>,
+    kind: Option<FlagKind>
+}
+
+impl FlagCollector {
+    pub fn collect<T: Decodable<FlagCollector, ()>>() -> Vec<Flag> {
+        let mut collector = FlagCollector { flags: Vec::new(), kind: None };
+        let _: Result<T, ()> = Decodable::decode(&mut collector);
+        collector.flags
+    }
+
+    // Only the outermost read of a field decides its kind; an `Option` or a
+    // `Vec` says more about the flag than the type it wraps.
+    fn record<T>(&mut self, kind: FlagKind, value: T) -> Result<T, ()> {
+        if self.kind.is_none() {
+            self.kind = Some(kind);
+        }
+
+        Ok(value)
+    }
+}
+
+impl Decoder<()> for FlagCollector {
+    fn read_nil(&mut self) -> Result<(), ()> { self.record(Switch, ()) }
+    fn read_uint(&mut self) -> Result<uint, ()> { self.record(Required, 0) }
+    fn read_u64(&mut self) -> Result<u64, ()> { self.record(Required, 0) }
+    fn read_u32(&mut self) -> Result<u32, ()> { self.record(Required, 0) }
+    fn read_u16(&mut self) -> Result<u16, ()> { self.record(Required, 0) }
+    fn read_u8(&mut self) -> Result<u8, ()> { self.record(Required, 0) }
+    fn read_int(&mut self) -> Result<int, ()> { self.record(Required, 0) }
+    fn read_i64(&mut self) -> Result<i64, ()> { self.record(Required, 0) }
+    fn read_i32(&mut self) -> Result<i32, ()> { self.record(Required, 0) }
+    fn read_i16(&mut self) -> Result<i16, ()> { self.record(Required, 0) }
+    fn read_i8(&mut self) -> Result<i8, ()> { self.record(Required, 0) }
+    fn read_bool(&mut self) -> Result<bool, ()> { self.record(Switch, false) }
+    fn read_f64(&mut self) -> Result<f64, ()> { self.record(Required, 0.0) }
+    fn read_f32(&mut self) -> Result<f32, ()> { self.record(Required, 0.0) }
+    fn read_char(&mut self) -> Result<char, ()> { self.record(Required, ' ') }
+    fn read_str(&mut self) -> Result<String, ()> { self.record(Required, String::new()) }
+
+    fn read_enum<T>(&mut self, _: &str, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+
+    fn read_enum_variant<T>(&mut self, _: &[&str], f: |&mut FlagCollector, uint| -> Result<T, ()>) -> Result<T, ()> {
+        try!(self.record(Required, ()));
+        f(self, 0)
+    }
+
+    fn read_enum_variant_arg<T>(&mut self, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+
+    fn read_enum_struct_variant<T>(&mut self, names: &[&str], f: |&mut FlagCollector, uint| -> Result<T, ()>) -> Result<T, ()> {
+        self.read_enum_variant(names, f)
+    }
+
+    fn read_enum_struct_variant_field<T>(&mut self, _: &str, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+
+    fn read_struct<T>(&mut self, _: &str, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+
+    fn read_struct_field<T>(&mut self, name: &str, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        self.kind = None;
+        let ret = f(self);
+        let kind = self.kind.take().unwrap_or(Required);
+
+        // Hammer collects the arguments that are not flags into `rest`
+        let kind = if name == "rest" { Rest } else { kind };
+
+        self.flags.push(Flag { name: name.to_str(), kind: kind, short: None });
+        ret
+    }
+
+    fn read_tuple<T>(&mut self, f: |&mut FlagCollector, uint| -> Result<T, ()>) -> Result<T, ()> {
+        f(self, 0)
+    }
+
+    fn read_tuple_arg<T>(&mut self, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+
+    fn read_tuple_struct<T>(&mut self, _: &str, f: |&mut FlagCollector, uint| -> Result<T, ()>) -> Result<T, ()> {
+        f(self, 0)
+    }
+
+    fn read_tuple_struct_arg<T>(&mut self, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+
+    fn read_option<T>(&mut self, f: |&mut FlagCollector, bool| -> Result<T, ()>) -> Result<T, ()> {
+        try!(self.record(Optional, ()));
+        f(self, false)
+    }
+
+    fn read_seq<T>(&mut self, f: |&mut FlagCollector, uint| -> Result<T, ()>) -> Result<T, ()> {
+        try!(self.record(Repeated, ()));
+        f(self, 0)
+    }
+
+    fn read_seq_elt<T>(&mut self, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+
+    fn read_map<T>(&mut self, f: |&mut FlagCollector, uint| -> Result<T, ()>) -> Result<T, ()> {
+        try!(self.record(Repeated, ()));
+        f(self, 0)
+    }
+
+    fn read_map_elt_key<T>(&mut self, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+
+    fn read_map_elt_val<T>(&mut self, _: uint, f: |&mut FlagCollector| -> Result<T, ()>) -> Result<T, ()> {
+        f(self)
+    }
+}
+
+/**
+ * The `--help` output of a command: a usage line naming its required flags,
+ * a description and every flag it accepts.
+ */
+pub struct Usage {
+    command: String,
+    about: String,
+    flags: Vec<Flag>
+}
+
+impl Usage {
+    pub fn new<T: FlagConfig + Decodable<FlagCollector, ()>>(command: &str, about: &str) -> Usage {
+        let config: FlagConfiguration = FlagConfig::config(None::<T>, FlagConfiguration::new());
+        let mut flags = FlagCollector::collect::<T>();
+
+        for flag in flags.mut_iter() {
+            flag.short = config.short_for(flag.name.as_slice());
+        }
+
+        Usage {
+            command: command.to_str(),
+            about: about.to_str(),
+            flags: flags
+        }
+    }
+
+    pub fn get_flags<'a>(&'a self) -> &'a [Flag] {
+        self.flags.as_slice()
+    }
+
+    /**
+     * Returns the first argument that looks like a flag, long or short, but
+     * is not one of the command's flags.
+     */
+    pub fn unknown_flag(&self, args: &[String]) -> Option<String> {
+        for arg in args.iter() {
+            let arg = arg.as_slice();
+
+            if arg == "--" {
+                break;
+            }
+
+            if arg == "--help" || arg == "-" || !arg.starts_with("-") {
+                continue;
+            }
+
+            let mut flags = self.flags.iter().filter(|flag| flag.kind != Rest);
+
+            if arg.starts_with("--") {
+                let name = arg.split('=').next().unwrap_or(arg);
+
+                if !flags.any(|flag| flag.get_name().as_slice() == name) {
+                    return Some(name.to_str());
+                }
+            } else {
+                let short = arg.char_at(1);
+
+                // e.g. a negative number given as a value
+                if short.is_digit() {
+                    continue;
+                }
+
+                if !flags.any(|flag| flag.short == Some(short)) {
+                    return Some(format!("-{}", short));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+impl Show for Usage {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        try!(write!(f, "Usage: {}", self.command));
+
+        for flag in self.flags.iter().filter(|flag| flag.kind == Required) {
+            try!(write!(f, " {}", flag.describe()));
+        }
+
+        try!(write!(f, " [options]"));
+
+        for flag in self.flags.iter().filter(|flag| flag.kind == Rest) {
+            try!(write!(f, " {}", flag.describe()));
+        }
+
+        try!(write!(f, "\n\n{}\n\nOptions:\n", self.about));
+
+        let mut lines: Vec<String> = self.flags.iter()
+            .filter(|flag| flag.kind != Rest)
+            .map(|flag| flag.describe())
+            .collect();
+        lines.push("--help".to_str());
+
+        for line in lines.iter() {
+            try!(write!(f, "    {}\n", line));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use hammer::{FlagConfig,FlagConfiguration};
+    use super::{Usage,Switch,Required,Optional};
+
+    #[deriving(Decodable)]
+    struct Options {
+        manifest_path: String,
+        target: Option<String>,
+        verbose: bool
+    }
+
+    impl FlagConfig for Options {
+        fn config(_: Option<Options>, config: FlagConfiguration) -> FlagConfiguration {
+            config.short("verbose", 'v')
+        }
+    }
+
+    #[test]
+    pub fn test_collect_flags() {
+        let usage = Usage::new::<Options>("cargo foo", "Do foo");
+        let kinds: Vec<(String, super::FlagKind)> = usage.get_flags().iter().map(|flag| (flag.get_name(), flag.get_kind())).collect();
+
+        assert_eq!(kinds, vec!(("--manifest-path".to_str(), Required), ("--target".to_str(), Optional), ("--verbose".to_str(), Switch)));
+    }
+
+    #[test]
+    pub fn test_usage_text() {
+        let usage = Usage::new::<Options>("cargo foo", "Do foo");
+
+        assert_eq!(usage.to_str().as_slice(), "Usage: cargo foo --manifest-path <manifest-path> [options]\n\n\
+                                                Do foo\n\n\
+                                                Options:\n    \
+                                                --manifest-path <manifest-path>\n    \
+                                                --target <target>\n    \
+                                                -v, --verbose\n    \
+                                                --help\n");
+    }
+
+    #[test]
+    pub fn test_unknown_flag() {
+        let usage = Usage::new::<Options>("cargo foo", "Do foo");
+        let args = vec!("--verbose".to_str(), "--target=x".to_str(), "--bogus".to_str());
+
+        assert_eq!(usage.unknown_flag(args.as_slice()), Some("--bogus".to_str()));
+    }
+
+    #[test]
+    pub fn test_unknown_short_flag() {
+        let usage = Usage::new::<Options>("cargo foo", "Do foo");
+        let args = vec!("-v".to_str(), "-x".to_str());
+
+        assert_eq!(usage.unknown_flag(args.as_slice()), Some("-x".to_str()));
+    }
+}
@@ -10,7 +10,7 @@ use std::vec::Vec;
 use std::fmt::Show;
 use ham = hamcrest;
 use cargo::core::shell;
-use cargo::util::{process,ProcessBuilder,CargoError};
+use cargo::util::{process,ProcessBuilder,CargoError,CargoResult};
 use cargo::util::result::ProcessError;
 
 pub mod paths;
@@ -122,6 +122,22 @@ pub fn project(name: &str) -> ProjectBuilder {
     ProjectBuilder::new(name, paths::root().join(name))
 }
 
+// Generates a project layout inside a git repository with a single commit
+pub fn git_repo(name: &str, callback: |ProjectBuilder| -> ProjectBuilder) -> CargoResult<ProjectBuilder> {
+    let mut git_project = project(name);
+    git_project = callback(git_project);
+    git_project.build();
+
+    log!(5, "git init");
+    try!(git_project.process("git").args(["init"]).exec_with_output());
+    log!(5, "building git project");
+    log!(5, "git add .");
+    try!(git_project.process("git").args(["add", "."]).exec_with_output());
+    log!(5, "git commit");
+    try!(git_project.process("git").args(["commit", "-m", "Initial commit"]).exec_with_output());
+    Ok(git_project)
+}
+
 // === Helpers ===
 
 pub fn mkdir_recursive(path: &Path) -> Result<(), String> {
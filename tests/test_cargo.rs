@@ -0,0 +1,162 @@
+use std::io;
+use std::io::fs;
+use std::str;
+use support::{ResultTest,project,execs,main_file,cargo_dir};
+use hamcrest::{assert_that,existing_file};
+
+fn setup() {
+}
+
+static SUBCOMMAND: &'static str = r#"#!/bin/sh
+echo "hello $@"
+exit 3
+"#;
+
+test!(cargo_dispatches_to_sibling_subcommand {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo").args(["compile"]), execs().with_status(0));
+    assert_that(&p.root().join("target/foo"), existing_file());
+})
+
+test!(cargo_dispatches_to_subcommand_on_path {
+    let p = project("foo")
+        .file("bin/cargo-hello", SUBCOMMAND);
+
+    p.build();
+    fs::chmod(&p.root().join("bin/cargo-hello"), io::UserRWX).unwrap();
+
+    assert_that(p.process("cargo")
+                  .args(["hello", "a", "b"])
+                  .extra_path(cargo_dir())
+                  .extra_path(p.root().join("bin")),
+                execs()
+                  .with_status(3)
+                  .with_stdout("hello a b\n"));
+})
+
+test!(cargo_dispatches_to_subcommand_in_configured_dir {
+    let p = project("foo")
+        .file("tools/cargo-hello", SUBCOMMAND);
+
+    let p = p.file(".cargo/config", format!(r#"
+            subcommand-paths = ["{}"]
+        "#, p.root().join("tools").display()).as_slice());
+
+    p.build();
+    fs::chmod(&p.root().join("tools/cargo-hello"), io::UserRWX).unwrap();
+
+    assert_that(p.cargo_process("cargo").args(["hello"]),
+                execs()
+                  .with_status(3)
+                  .with_stdout("hello \n"));
+})
+
+test!(cargo_list_includes_builtin_and_discovered_commands {
+    let p = project("foo");
+
+    let output = p.cargo_process("cargo").args(["--list"]).exec_with_output().assert();
+    let stdout = str::from_utf8_lossy(output.output.as_slice()).to_str();
+
+    assert!(stdout.as_slice().starts_with("Installed commands:\n"));
+    assert!(stdout.as_slice().contains("    compile\n"));
+    assert!(stdout.as_slice().contains("    locate-project\n"));
+})
+
+test!(cargo_suggests_closest_command {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("cargo").args(["compil"]),
+                execs()
+                  .with_status(1)
+                  .with_stderr("No such subcommand `compil`\n\nDid you mean `compile`?"));
+})
+
+test!(cargo_reports_unknown_command {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("cargo").args(["xyzzy-plugh"]),
+                execs()
+                  .with_status(1)
+                  .with_stderr("No such subcommand `xyzzy-plugh`"));
+})
+
+static COMPILE_USAGE: &'static str = "Usage: cargo compile [options]
+
+Compile the current project and its dependencies
+
+Options:
+    --manifest-path <manifest-path>
+    -j, --jobs <jobs>
+    --release
+    --target <target>
+    --features <features>
+    --no-default-features
+    --all
+    --help
+";
+
+test!(cargo_subcommand_help {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("cargo-compile").args(["--help"]),
+                execs()
+                  .with_status(0)
+                  .with_stdout(COMPILE_USAGE));
+})
+
+test!(cargo_help_routes_to_subcommand {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("cargo").args(["help", "compile"]),
+                execs()
+                  .with_status(0)
+                  .with_stdout(COMPILE_USAGE));
+})
+
+test!(cargo_help_for_builtin {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("cargo").args(["help", "config-for-key"]),
+                execs()
+                  .with_status(0)
+                  .with_stdout("Usage: cargo config-for-key --key <key> [options]
+
+Print the configured value of a key
+
+Options:
+    --key <key>
+    -h, --human
+    --help
+"));
+})
+
+test!(cargo_subcommand_unknown_flag {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("cargo-compile").args(["--bogus"]),
+                execs()
+                  .with_status(1)
+                  .with_stderr(format!("Unknown flag `--bogus`\n\n{}", COMPILE_USAGE).as_slice()));
+})
+
+test!(cargo_subcommand_unknown_short_flag {
+    let p = project("foo");
+
+    assert_that(p.cargo_process("cargo-compile").args(["-x"]),
+                execs()
+                  .with_status(1)
+                  .with_stderr(format!("Unknown flag `-x`\n\n{}", COMPILE_USAGE).as_slice()));
+})
@@ -0,0 +1,126 @@
+use std::io::fs;
+use std::io::UserRWX;
+use support::{ResultTest,ProjectBuilder,project,execs,main_file,cargo_dir};
+use cargo::util::realpath;
+use hamcrest::{assert_that,existing_file};
+
+fn setup() {
+}
+
+fn foo_with_bar() -> ProjectBuilder {
+    let p = project("foo");
+    let bar = p.root().join("bar");
+
+    p.file(".cargo/config", format!(r#"
+            paths = ["{}"]
+        "#, bar.display()).as_slice())
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            bar = "0.5.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("bar/src/bar.rs", r#"
+            pub fn gimme() -> String {
+                "test passed".to_str()
+            }
+        "#)
+}
+
+fn has_bar(dir: &Path) -> bool {
+    fs::readdir(dir).assert().iter().any(|path| path.filename_str().unwrap().starts_with("libbar-"))
+}
+
+test!(cargo_clean_removes_target_dir {
+    let p = foo_with_bar();
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+    assert_that(&p.root().join("target/foo"), existing_file());
+
+    assert_that(p.process("cargo-clean").extra_path(cargo_dir()), execs().with_status(0));
+    assert!(!p.root().join("target").exists());
+})
+
+test!(cargo_clean_release {
+    let p = foo_with_bar();
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+    assert_that(p.process("cargo-compile").args(["--release"]).extra_path(cargo_dir()), execs().with_status(0));
+    assert_that(&p.root().join("target/release/foo"), existing_file());
+
+    assert_that(p.process("cargo-clean").args(["--release"]).extra_path(cargo_dir()), execs().with_status(0));
+    assert!(!p.root().join("target/release").exists());
+    assert_that(&p.root().join("target/foo"), existing_file());
+})
+
+test!(cargo_clean_one_package {
+    let p = foo_with_bar();
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+    assert!(has_bar(&p.root().join("target/deps")));
+
+    assert_that(p.process("cargo-clean").args(["--package", "bar"]).extra_path(cargo_dir()), execs().with_status(0));
+    assert!(!has_bar(&p.root().join("target/deps")));
+    assert_that(&p.root().join("target/foo"), existing_file());
+
+    // Only bar needs to be compiled again
+    let bar = realpath(&p.root().join("bar")).assert();
+    let main = realpath(&p.root()).assert();
+
+    assert_that(p.process("cargo-compile").extra_path(cargo_dir()),
+                execs()
+                  .with_status(0)
+                  .with_stdout(format!("Compiling bar v0.5.0 (file:{})\nFresh foo v0.5.0 (file:{})\n",
+                                       bar.display(), main.display())));
+})
+
+test!(cargo_clean_refuses_a_package_name_with_a_path {
+    let p = foo_with_bar();
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+    fs::mkdir_recursive(&p.root().join("outside"), UserRWX).assert();
+
+    assert_that(p.process("cargo-clean").args(["--package", "../../outside"]).extra_path(cargo_dir()),
+                execs()
+                  .with_status(101)
+                  .with_stderr("`../../outside` is not a valid package name\n"));
+
+    assert!(p.root().join("outside").exists());
+    assert!(has_bar(&p.root().join("target/deps")));
+})
+
+test!(cargo_clean_refuses_a_directory_outside_the_project {
+    let p = foo_with_bar();
+    let root = realpath(&p.root()).assert();
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(p.process("cargo-clean").args(["--target", "../.."]).extra_path(cargo_dir()),
+                execs()
+                  .with_status(101)
+                  .with_stderr(format!("Refusing to remove `{}`, which is outside of the project root `{}`\n",
+                                       root.dir_path().display(), root.display())));
+
+    assert!(p.root().join("target/deps").exists());
+})
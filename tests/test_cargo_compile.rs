@@ -1,7 +1,14 @@
-use support::{ResultTest,project,execs,main_file};
+use std::io;
+use std::io::File;
+use std::io::fs;
+use std::io::fs::readdir;
+use std::io::process::ExitStatus;
+use std::str;
+use support::{ResultTest,ProjectBuilder,project,execs,main_file,cargo_dir};
 use hamcrest::{assert_that,existing_file};
 use cargo;
-use cargo::util::{process,realpath};
+use cargo::util::{process,realpath,CargoError};
+use cargo::util::result::ProcessError;
 
 fn setup() {
 }
@@ -64,7 +71,7 @@ test!(cargo_compile_with_invalid_code {
     assert_that(p.cargo_process("cargo-compile"),
         execs()
         .with_status(101)
-        .with_stderr(format!("src/foo.rs:1:1: 1:8 error: expected item but found `invalid`\nsrc/foo.rs:1 invalid rust code!\n             ^~~~~~~\nfailed to execute: `rustc src/foo.rs --crate-type bin --out-dir {} -L {}`", target.display(), target.join("deps").display()).as_slice()));
+        .with_stderr(format!("src/foo.rs:1:1: 1:8 error: expected item but found `invalid`\nsrc/foo.rs:1 invalid rust code!\n             ^~~~~~~\nfailed to execute: `rustc src/foo.rs --crate-type bin --out-dir {} -g -L {}`", target.display(), target.join("deps").display()).as_slice()));
 })
 
 test!(cargo_compile_with_warnings_in_the_root_package {
@@ -285,3 +292,1075 @@ test!(cargo_compile_with_nested_deps_longhand {
 })
 
 // test!(compiling_project_with_invalid_manifest)
+
+test!(cargo_compile_with_path_deps {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            path = "bar"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.baz]
+
+            version = "0.5.0"
+            path = "../baz"
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("bar/src/bar.rs", r#"
+            extern crate baz;
+
+            pub fn gimme() -> String {
+                baz::gimme()
+            }
+        "#)
+        .file("baz/Cargo.toml", r#"
+            [project]
+
+            name = "baz"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "baz"
+        "#)
+        .file("baz/src/baz.rs", r#"
+            pub fn gimme() -> String {
+                "test passed".to_str()
+            }
+        "#);
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(&p.root().join("target/foo"), existing_file());
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("test passed\n"));
+})
+
+test!(cargo_compile_with_missing_path_dep {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            path = "bar"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    let bar = realpath(&p.root()).assert().join("bar");
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr(format!("Could not read the path dependency at `{}`", bar.display())));
+})
+
+test!(cargo_compile_ignores_dev_deps {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            path = "bar"
+
+            [dev-dependencies]
+
+            hamcrest = "1.0.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dev-dependencies]
+
+            quickcheck = "0.1.0"
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("bar/src/bar.rs", r#"
+            pub fn gimme() -> String {
+                "test passed".to_str()
+            }
+        "#);
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("test passed\n"));
+
+    let lock = File::open(&p.root().join("Cargo.lock")).read_to_str().assert();
+    assert!(!lock.as_slice().contains("hamcrest"), "Cargo.lock was {}", lock);
+})
+
+test!(cargo_compile_ignores_dev_dep_sources {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            path = "bar"
+
+            [dev-dependencies.hamcrest]
+
+            version = "1.0.0"
+            git = "file:///does/not/exist"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dev-dependencies.quickcheck]
+
+            version = "0.1.0"
+            path = "missing"
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("bar/src/bar.rs", r#"
+            pub fn gimme() -> String {
+                "test passed".to_str()
+            }
+        "#);
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0).with_stdout(
+        format!("Compiling bar v0.5.0 (file:{})\nCompiling foo v0.5.0 (file:{})\n",
+                realpath(&p.root().join("bar")).assert().display(), realpath(&p.root()).assert().display())));
+})
+
+/**
+ * Package `foo` depending on `bar`, which it finds in its own `bar`
+ * directory through the `paths` config.
+ */
+fn foo_with_bar() -> ProjectBuilder {
+    let p = project("foo");
+    let bar = p.root().join("bar");
+
+    p
+        .file(".cargo/config", format!(r#"
+            paths = ["{}"]
+        "#, bar.display()).as_slice())
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            bar = "0.5.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("bar/src/bar.rs", r#"
+            pub fn gimme() -> String {
+                "test passed".to_str()
+            }
+        "#)
+}
+
+test!(cargo_compile_skips_fresh_packages {
+    let p = foo_with_bar();
+
+    let bar = realpath(&p.root().join("bar")).assert();
+    let main = realpath(&p.root()).assert();
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_stdout(format!("Compiling bar v0.5.0 (file:{})\nCompiling foo v0.5.0 (file:{})\n",
+                             bar.display(), main.display())));
+
+    assert_that(p.process("cargo-compile").extra_path(cargo_dir()),
+        execs()
+        .with_stdout(format!("Fresh bar v0.5.0 (file:{})\nFresh foo v0.5.0 (file:{})\n",
+                             bar.display(), main.display())));
+
+    File::create(&p.root().join("src/foo.rs"))
+        .write_str(main_file(r#""{} again", bar::gimme()"#, ["bar"]).as_slice()).assert();
+
+    assert_that(p.process("cargo-compile").extra_path(cargo_dir()),
+        execs()
+        .with_stdout(format!("Fresh bar v0.5.0 (file:{})\nCompiling foo v0.5.0 (file:{})\n",
+                             bar.display(), main.display())));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("test passed again\n"));
+})
+
+test!(cargo_compile_rebuilds_deleted_outputs {
+    let p = foo_with_bar();
+
+    let bar = realpath(&p.root().join("bar")).assert();
+    let main = realpath(&p.root()).assert();
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    fs::unlink(&p.root().join("target/foo")).assert();
+
+    assert_that(p.process("cargo-compile").extra_path(cargo_dir()),
+        execs()
+        .with_stdout(format!("Fresh bar v0.5.0 (file:{})\nCompiling foo v0.5.0 (file:{})\n",
+                             bar.display(), main.display())));
+
+    for file in readdir(&p.root().join("target/deps")).assert().iter() {
+        if file.filename_str().map(|name| name.starts_with("libbar-")).unwrap_or(false) {
+            fs::unlink(file).assert();
+        }
+    }
+
+    assert_that(p.process("cargo-compile").extra_path(cargo_dir()),
+        execs()
+        .with_stdout(format!("Compiling bar v0.5.0 (file:{})\nFresh foo v0.5.0 (file:{})\n",
+                             bar.display(), main.display())));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("test passed\n"));
+})
+
+test!(cargo_compile_reports_every_failed_dep {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            path = "bar"
+
+            [dependencies.baz]
+
+            version = "0.5.0"
+            path = "baz"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{} {}", bar::gimme(), baz::gimme()"#, ["bar", "baz"]).as_slice())
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("bar/src/bar.rs", "invalid rust code!")
+        .file("baz/Cargo.toml", r#"
+            [project]
+
+            name = "baz"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "baz"
+        "#)
+        .file("baz/src/baz.rs", "invalid rust code!");
+
+    let output = match p.cargo_process("cargo-compile").args(["-j", "2"]).exec_with_output() {
+        Ok(_) => fail!("cargo-compile should have failed"),
+        Err(CargoError { kind: ProcessError(_, Some(output)), .. }) => output,
+        Err(err) => fail!("could not run cargo-compile: {}", err)
+    };
+
+    let stderr = str::from_utf8_lossy(output.error.as_slice()).to_str();
+
+    assert_eq!(output.status, ExitStatus(101));
+    assert!(stderr.as_slice().contains("Failed to compile bar v0.5.0"), "stderr was {}", stderr);
+    assert!(stderr.as_slice().contains("Failed to compile baz v0.5.0"), "stderr was {}", stderr);
+})
+
+test!(cargo_compile_release_profile {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile").args(["--release"]), execs().with_status(0));
+
+    assert_that(&p.root().join("target/release/foo"), existing_file());
+    assert!(!p.root().join("target/foo").exists());
+
+    assert_that(
+      process("foo").extra_path(p.root().join("target/release")),
+      execs().with_stdout("i am foo\n"));
+})
+
+test!(cargo_compile_with_profile_rustc_args {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [profile.dev]
+
+            rustc-args = ["--cfg", "shout"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", r#"
+            #[cfg(shout)]
+            fn main() { println!("I AM FOO"); }
+
+            #[cfg(not(shout))]
+            fn main() { println!("i am foo"); }
+        "#);
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(
+      process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("I AM FOO\n"));
+})
+
+test!(cargo_compile_with_unknown_profile {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [profile.fast]
+
+            opt-level = 3
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr("Unknown profile `fast`; the profiles are `dev`, `release` and `test`"));
+})
+
+test!(cargo_compile_with_invalid_profile_setting {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [profile.release]
+
+            opt-level = "fast"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr("`opt-level` in [profile.release] must be an integer"));
+})
+
+fn host_triple() -> String {
+    let output = process("rustc").args(["-v"]).exec_with_output().assert();
+    let version = str::from_utf8_lossy(output.output.as_slice()).to_str();
+
+    version.as_slice().lines()
+        .find(|line| line.starts_with("host: "))
+        .map(|line| line.slice_from(6).trim().to_str())
+        .expect("rustc -v did not print the host triple")
+}
+
+test!(cargo_compile_for_target {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    let triple = host_triple();
+
+    assert_that(p.cargo_process("cargo-compile").args(["--target", triple.as_slice()]), execs().with_status(0));
+
+    assert_that(&p.root().join("target").join(triple.as_slice()).join("foo"), existing_file());
+    assert!(!p.root().join("target/foo").exists());
+
+    assert_that(
+      process("foo").extra_path(p.root().join("target").join(triple.as_slice())),
+      execs().with_stdout("i am foo\n"));
+})
+
+test!(cargo_compile_for_target_with_configured_linker {
+    let triple = host_triple();
+
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice())
+        .file(".cargo/config", format!(r#"
+            [target.{}]
+
+            linker = "not-a-real-linker"
+        "#, triple).as_slice());
+
+    let output = match p.cargo_process("cargo-compile").args(["--target", triple.as_slice()]).exec_with_output() {
+        Ok(_) => fail!("cargo-compile should have failed"),
+        Err(CargoError { kind: ProcessError(_, Some(output)), .. }) => output,
+        Err(err) => fail!("could not run cargo-compile: {}", err)
+    };
+
+    let stderr = str::from_utf8_lossy(output.error.as_slice()).to_str();
+
+    assert_eq!(output.status, ExitStatus(101));
+    assert!(stderr.as_slice().contains(format!("--target {} -C linker=not-a-real-linker", triple).as_slice()),
+            "stderr was {}", stderr);
+})
+
+test!(cargo_compile_for_target_builds_plugins_for_host {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            path = "bar"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice())
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+            plugin = true
+        "#)
+        .file("bar/src/bar.rs", "pub fn gimme() {}");
+
+    let triple = host_triple();
+
+    assert_that(p.cargo_process("cargo-compile").args(["--target", triple.as_slice()]), execs().with_status(0));
+
+    let has_bar = |dir: Path| {
+        readdir(&dir).assert().iter().any(|file| file.filename_str().unwrap().starts_with("libbar"))
+    };
+
+    assert!(has_bar(p.root().join("target/deps")));
+    assert!(!has_bar(p.root().join("target").join(triple.as_slice()).join("deps")));
+    assert_that(&p.root().join("target").join(triple.as_slice()).join("foo"), existing_file());
+})
+
+test!(cargo_compile_with_features {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [features]
+
+            loud = []
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", r#"
+            #[cfg(feature = "loud")]
+            fn main() { println!("I AM FOO"); }
+
+            #[cfg(not(feature = "loud"))]
+            fn main() { println!("i am foo"); }
+        "#);
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(
+      process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("i am foo\n"));
+
+    assert_that(p.process("cargo-compile").extra_path(cargo_dir()).args(["--features", "loud"]),
+        execs().with_status(0));
+
+    assert_that(
+      process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("I AM FOO\n"));
+})
+
+test!(cargo_compile_with_optional_deps {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [features]
+
+            default = ["fancy"]
+            fancy = ["bar"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            path = "bar"
+            optional = true
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", r#"
+            #[cfg(feature = "fancy")]
+            extern crate bar;
+
+            #[cfg(feature = "fancy")]
+            fn main() { println!("{}", bar::gimme()); }
+
+            #[cfg(not(feature = "fancy"))]
+            fn main() { println!("plain"); }
+        "#)
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("bar/src/bar.rs", r#"
+            pub fn gimme() -> &'static str {
+                "fancy"
+            }
+        "#);
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(
+      process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("fancy\n"));
+
+    assert_that(p.process("cargo-compile").extra_path(cargo_dir()).args(["--no-default-features"]),
+        execs().with_status(0)
+        .with_stdout(format!("Compiling foo v0.5.0 (file:{})\n", realpath(&p.root()).assert().display())));
+
+    assert_that(
+      process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("plain\n"));
+})
+
+test!(cargo_compile_with_unknown_feature {
+    let p = project("foo")
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile").args(["--features", "nope"]),
+        execs()
+        .with_status(101)
+        .with_stderr("Package `foo` does not have the feature `nope`"));
+})
+
+test!(cargo_compile_with_invalid_feature {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [features]
+
+            tls = ["openssl"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr("Feature `tls` enables `openssl`, which is neither a feature nor an optional dependency"));
+})
+
+test!(cargo_compile_with_features_not_a_table {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            features = ["tls"]
+
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr("features must be a table"));
+})
+
+test!(cargo_compile_with_invalid_optional_dependency {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            optional = "yes"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr("`optional` in dependency `bar` must be true or false"));
+})
+
+test!(cargo_compile_runs_build_script {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+            build = "build.rs"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("build.rs", r#"
+            use std::os;
+            use std::io::File;
+
+            fn main() {
+                let out = Path::new(os::getenv("OUT_DIR").unwrap());
+                let env = format!("{} {}", os::getenv("TARGET").unwrap(), os::getenv("PROFILE").unwrap());
+                File::create(&out.join("env")).write_str(env.as_slice()).unwrap();
+            }
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    let env = File::open(&p.root().join("target/build/foo/out/env")).read_to_str().assert();
+    assert_eq!(env, format!("{} dev", host_triple()));
+})
+
+test!(cargo_compile_links_native_libs_from_build_script {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+            build = "sh build.sh"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("build.sh", r#"
+            cc -c native.c -o $OUT_DIR/native.o && ar crs $OUT_DIR/libnative.a $OUT_DIR/native.o || exit 1
+            echo "cargo:rustc-flags=-L $OUT_DIR -l native"
+        "#)
+        .file("native.c", "int native_answer() { return 42; }\n")
+        .file("src/foo.rs", r#"
+            extern {
+                fn native_answer() -> i32;
+            }
+
+            fn main() {
+                println!("{}", unsafe { native_answer() });
+            }
+        "#);
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(
+      process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("42\n"));
+})
+
+test!(cargo_compile_with_failing_build_script {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+            build = "sh build.sh"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("build.sh", "echo 'no C compiler found' >&2; exit 1\n")
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    let output = match p.cargo_process("cargo-compile").exec_with_output() {
+        Ok(_) => fail!("cargo-compile should have failed"),
+        Err(CargoError { kind: ProcessError(_, Some(output)), .. }) => output,
+        Err(err) => fail!("could not run cargo-compile: {}", err)
+    };
+
+    let stderr = str::from_utf8_lossy(output.error.as_slice()).to_str();
+
+    assert_eq!(output.status, ExitStatus(101));
+    assert!(stderr.as_slice().contains("failed to run the build script for foo v0.5.0"), "stderr was {}", stderr);
+    assert!(stderr.as_slice().contains("no C compiler found"), "stderr was {}", stderr);
+})
+
+test!(cargo_compile_with_missing_build_program {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+            build = "no-such-build-tool --all"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    let output = match p.cargo_process("cargo-compile").exec_with_output() {
+        Ok(_) => fail!("cargo-compile should have failed"),
+        Err(CargoError { kind: ProcessError(_, Some(output)), .. }) => output,
+        Err(err) => fail!("could not run cargo-compile: {}", err)
+    };
+
+    let stderr = str::from_utf8_lossy(output.error.as_slice()).to_str();
+
+    assert_eq!(output.status, ExitStatus(101));
+    assert!(stderr.as_slice().contains("failed to run the build script for foo v0.5.0: `no-such-build-tool --all` could not be started"), "stderr was {}", stderr);
+})
+
+test!(cargo_compile_with_recursive_paths {
+    let mut p = project("foo");
+    let checkouts = p.root().join("checkouts");
+
+    p = p
+        .file(".cargo/config", format!(r#"
+            recursive-paths = ["{}"]
+            paths-ignore = ["old"]
+        "#, checkouts.display()).as_slice())
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            bar = "0.5.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("checkouts/libs/bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("checkouts/libs/bar/src/bar.rs", r#"
+            pub fn gimme() -> String {
+                "test passed".to_str()
+            }
+        "#)
+        .file("checkouts/old/bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("checkouts/old/bar/src/bar.rs", "this is not rust")
+        .file("checkouts/broken/Cargo.toml", "[project");
+
+    let bar = realpath(&checkouts.join("libs/bar")).assert();
+    let main = realpath(&p.root()).assert();
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_stdout(format!("Compiling bar v0.5.0 (file:{})\nCompiling foo v0.5.0 (file:{})\n",
+                             bar.display(), main.display()))
+        .with_stderr(format!("warning: skipping the manifest at `{}`: Cargo.toml is not valid Toml\n",
+                             checkouts.join("broken/Cargo.toml").display())));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("test passed\n"));
+})
+
+test!(cargo_compile_with_recursive_paths_and_an_unreadable_directory {
+    let mut p = project("foo");
+    let checkouts = p.root().join("checkouts");
+
+    p = p
+        .file(".cargo/config", format!(r#"
+            recursive-paths = ["{}"]
+        "#, checkouts.display()).as_slice())
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            bar = "0.5.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("checkouts/libs/bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("checkouts/libs/bar/src/bar.rs", r#"
+            pub fn gimme() -> String {
+                "test passed".to_str()
+            }
+        "#)
+        .file("checkouts/private/README", "");
+    p.build();
+
+    let private = checkouts.join("private");
+    fs::chmod(&private, io::UserWrite).assert();
+
+    let result = p.process("cargo-compile").extra_path(cargo_dir()).exec_with_output();
+    fs::chmod(&private, io::UserRWX).assert();
+
+    // The rest of the root is still searched
+    assert_eq!(result.assert().status, ExitStatus(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("test passed\n"));
+})
+
+test!(cargo_compile_with_recursive_paths_beyond_the_depth {
+    let mut p = project("foo");
+    let checkouts = p.root().join("checkouts");
+
+    p = p
+        .file(".cargo/config", format!(r#"
+            recursive-paths = ["{}"]
+            paths-depth = "1"
+        "#, checkouts.display()).as_slice())
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            bar = "0.5.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("checkouts/libs/bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("checkouts/libs/bar/src/bar.rs", "");
+
+    let output = match p.cargo_process("cargo-compile").exec_with_output() {
+        Ok(_) => fail!("cargo-compile should have failed"),
+        Err(CargoError { kind: ProcessError(_, Some(output)), .. }) => output,
+        Err(err) => fail!("could not run cargo-compile: {}", err)
+    };
+
+    let stderr = str::from_utf8_lossy(output.error.as_slice()).to_str();
+
+    // `bar` is two directories below the root, one more than searched
+    assert_eq!(output.status, ExitStatus(101));
+    assert!(stderr.as_slice().starts_with("No package named `bar` found"), "stderr was {}", stderr);
+})
+
+test!(cargo_compile_with_invalid_paths_depth {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            paths-depth = "deep"
+        "#)
+        .file("Cargo.toml", basic_bin_manifest("foo").as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr("`paths-depth` must be a number of directories, but it was `deep`"));
+})
@@ -1,26 +1,11 @@
-use support::{ProjectBuilder,ResultTest,project,execs,main_file};
+use std::io::File;
+use support::{ResultTest,project,execs,main_file,cargo_dir,git_repo};
 use hamcrest::{assert_that,existing_file};
 use cargo;
-use cargo::util::{CargoResult,process};
 
 fn setup() {
 }
 
-fn git_repo(name: &str, callback: |ProjectBuilder| -> ProjectBuilder) -> CargoResult<ProjectBuilder> {
-    let mut git_project = project(name);
-    git_project = callback(git_project);
-    git_project.build();
-
-    log!(5, "git init");
-    try!(git_project.process("git").args(["init"]).exec_with_output());
-    log!(5, "building git project");
-    log!(5, "git add .");
-    try!(git_project.process("git").args(["add", "."]).exec_with_output());
-    log!(5, "git commit");
-    try!(git_project.process("git").args(["commit", "-m", "Initial commit"]).exec_with_output());
-    Ok(git_project)
-}
-
 test!(cargo_compile_simple_git_dep {
     let project = project("foo");
     let git_project = git_repo("dep1", |project| {
@@ -77,3 +62,403 @@ test!(cargo_compile_simple_git_dep {
       cargo::util::process("foo").extra_path(project.root().join("target")),
       execs().with_stdout("hello world\n"));
 })
+
+test!(cargo_compile_git_dep_uses_locked_revision {
+    let project = project("foo");
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+
+                name = "dep1"
+                version = "0.5.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep1"
+            "#)
+            .file("src/dep1.rs", r#"
+                pub fn hello() -> &'static str {
+                    "hello world"
+                }
+            "#)
+    }).assert();
+
+    let project = project
+        .file("Cargo.toml", format!(r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.dep1]
+
+            version = "0.5.0"
+            git = "file://{}"
+
+            [[bin]]
+
+            name = "foo"
+        "#, git_project.root().display()))
+        .file("src/foo.rs", main_file(r#""{}", dep1::hello()"#, ["dep1"]));
+
+    assert_that(project.cargo_process("cargo-compile"), execs().with_status(0));
+    assert_that(&project.root().join("Cargo.lock"), existing_file());
+
+    // Move the dependency's master forward; the lockfile keeps the old revision
+    File::create(&git_project.root().join("src/dep1.rs")).write_str(r#"
+        pub fn hello() -> &'static str {
+            "goodbye world"
+        }
+    "#).assert();
+    git_project.process("git").args(["commit", "-a", "-m", "Second commit"]).exec_with_output().assert();
+
+    assert_that(project.process("cargo-compile").extra_path(cargo_dir()), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(project.root().join("target")),
+      execs().with_stdout("hello world\n"));
+})
+
+test!(cargo_compile_git_dep_tag {
+    let project = project("foo");
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+
+                name = "dep1"
+                version = "0.5.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep1"
+            "#)
+            .file("src/dep1.rs", r#"
+                pub fn hello() -> &'static str {
+                    "hello world"
+                }
+            "#)
+    }).assert();
+
+    git_project.process("git").args(["tag", "-a", "v0.5.0", "-m", "Release"]).exec_with_output().assert();
+
+    File::create(&git_project.root().join("src/dep1.rs")).write_str(r#"
+        pub fn hello() -> &'static str {
+            "unreleased"
+        }
+    "#).assert();
+    git_project.process("git").args(["commit", "-a", "-m", "Second commit"]).exec_with_output().assert();
+
+    let project = project
+        .file("Cargo.toml", format!(r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.dep1]
+
+            version = "0.5.0"
+            git = "file://{}"
+            tag = "v0.5.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#, git_project.root().display()))
+        .file("src/foo.rs", main_file(r#""{}", dep1::hello()"#, ["dep1"]));
+
+    assert_that(project.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(project.root().join("target")),
+      execs().with_stdout("hello world\n"));
+})
+
+test!(cargo_compile_git_dep_with_tag_and_rev {
+    let project = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.dep1]
+
+            version = "0.5.0"
+            git = "file:///dep1"
+            tag = "v0.5.0"
+            rev = "0123456789abcdef"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []));
+
+    assert_that(project.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr("Dependency `dep1` specifies more than one of `branch`, `tag` and `rev`; only one may be given"));
+})
+
+test!(cargo_compile_git_dep_in_subdirectory {
+    let project = project("foo");
+    let git_project = git_repo("deps", |project| {
+        project
+            .file("dep1/Cargo.toml", r#"
+                [project]
+
+                name = "dep1"
+                version = "0.5.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep1"
+            "#)
+            .file("dep1/src/dep1.rs", r#"
+                pub fn hello() -> &'static str {
+                    "hello world"
+                }
+            "#)
+            .file("dep2/Cargo.toml", r#"
+                [project]
+
+                name = "dep2"
+                version = "0.5.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep2"
+            "#)
+            .file("dep2/src/dep2.rs", r#"
+                pub fn world() -> &'static str {
+                    "world"
+                }
+            "#)
+            .file("dep2/target/Cargo.toml", "not a manifest")
+    }).assert();
+
+    let project = project
+        .file("Cargo.toml", format!(r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.dep1]
+
+            version = "0.5.0"
+            git = "file://{}"
+
+            [[bin]]
+
+            name = "foo"
+        "#, git_project.root().display()))
+        .file("src/foo.rs", main_file(r#""{}", dep1::hello()"#, ["dep1"]));
+
+    let root = project.root();
+    let git_root = git_project.root();
+
+    assert_that(project.cargo_process("cargo-compile"),
+        execs()
+        .with_stdout(format!("Updating git repository `file:{}`\nCompiling dep1 v0.5.0 (file:{})\nCompiling foo v0.5.0 (file:{})\n",
+                             git_root.display(), git_root.display(), root.display()))
+        .with_stderr(""));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(project.root().join("target")),
+      execs().with_stdout("hello world\n"));
+})
+
+test!(cargo_compile_git_dep_with_duplicate_packages {
+    let project = project("foo");
+    let git_project = git_repo("deps", |project| {
+        project
+            .file("a/Cargo.toml", r#"
+                [project]
+
+                name = "dep1"
+                version = "0.5.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep1"
+            "#)
+            .file("a/src/dep1.rs", "")
+            .file("b/Cargo.toml", r#"
+                [project]
+
+                name = "dep1"
+                version = "0.6.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep1"
+            "#)
+            .file("b/src/dep1.rs", "")
+    }).assert();
+
+    let project = project
+        .file("Cargo.toml", format!(r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.dep1]
+
+            version = "0.5.0"
+            git = "file://{}"
+
+            [[bin]]
+
+            name = "foo"
+        "#, git_project.root().display()))
+        .file("src/foo.rs", main_file(r#""i am foo""#, []));
+
+    assert_that(project.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr(format!("unable to list packages from source: The git repo at file:{} contains more than one package named `dep1`, at `a/Cargo.toml` and `b/Cargo.toml`",
+                             git_project.root().display())));
+})
+
+test!(cargo_compile_git_dep_with_changed_branch {
+    let project = project("foo");
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+
+                name = "dep1"
+                version = "0.5.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep1"
+            "#)
+            .file("src/dep1.rs", r#"
+                pub fn hello() -> &'static str {
+                    "hello world"
+                }
+            "#)
+    }).assert();
+
+    // A branch that moves on from master
+    git_project.process("git").args(["checkout", "-b", "next"]).exec_with_output().assert();
+    File::create(&git_project.root().join("src/dep1.rs")).write_str(r#"
+        pub fn hello() -> &'static str {
+            "goodbye world"
+        }
+    "#).assert();
+    git_project.process("git").args(["commit", "-a", "-m", "Second commit"]).exec_with_output().assert();
+    git_project.process("git").args(["checkout", "master"]).exec_with_output().assert();
+
+    let manifest = |reference: &str| format!(r#"
+        [project]
+
+        name = "foo"
+        version = "0.5.0"
+        authors = ["wycats@example.com"]
+
+        [dependencies.dep1]
+
+        version = "0.5.0"
+        git = "file://{}"
+        {}
+
+        [[bin]]
+
+        name = "foo"
+    "#, git_project.root().display(), reference);
+
+    let project = project
+        .file("Cargo.toml", manifest("").as_slice())
+        .file("src/foo.rs", main_file(r#""{}", dep1::hello()"#, ["dep1"]));
+
+    assert_that(project.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(project.root().join("target")),
+      execs().with_stdout("hello world\n"));
+
+    // The lockfile was written for master, so it is not reused for the branch
+    File::create(&project.root().join("Cargo.toml")).write_str(manifest(r#"branch = "next""#).as_slice()).assert();
+
+    assert_that(project.process("cargo-compile").extra_path(cargo_dir()), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(project.root().join("target")),
+      execs().with_stdout("goodbye world\n"));
+})
+
+test!(cargo_compile_git_dep_with_symlinks_and_fixtures {
+    let project = project("foo");
+    let git_project = git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+
+                name = "dep1"
+                version = "0.5.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep1"
+            "#)
+            .file("src/dep1.rs", r#"
+                pub fn hello() -> &'static str {
+                    "hello world"
+                }
+            "#)
+            .file("tests/fixtures/broken/Cargo.toml", "[project")
+    }).assert();
+
+    // A link back to the root of the repository
+    git_project.process("ln").args(["-s", ".", "self"]).exec_with_output().assert();
+    git_project.process("git").args(["add", "self"]).exec_with_output().assert();
+    git_project.process("git").args(["commit", "-m", "Add a symlink"]).exec_with_output().assert();
+
+    let project = project
+        .file("Cargo.toml", format!(r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.dep1]
+
+            version = "0.5.0"
+            git = "file://{}"
+
+            [[bin]]
+
+            name = "foo"
+        "#, git_project.root().display()))
+        .file("src/foo.rs", main_file(r#""{}", dep1::hello()"#, ["dep1"]));
+
+    assert_that(project.cargo_process("cargo-compile"),
+        execs()
+        .with_status(0)
+        .with_stderr("warning: skipping the manifest at `tests/fixtures/broken/Cargo.toml`: Cargo.toml is not valid Toml\n"));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(project.root().join("target")),
+      execs().with_stdout("hello world\n"));
+})
@@ -0,0 +1,170 @@
+use std::io::{fs,File,Listener,Acceptor,BufferedReader};
+use std::io::net::tcp::TcpListener;
+use support::{ProjectBuilder,ResultTest,project,execs,main_file,cargo_dir,paths};
+use hamcrest::{assert_that,existing_file};
+use cargo;
+use cargo::sources::registry::sha256;
+
+fn setup() {
+}
+
+// Builds a registry on the local file system containing `bar v0.5.0`. The
+// index records `cksum`, or the real checksum of the tarball if it is None.
+fn registry(cksum: Option<&str>) -> ProjectBuilder {
+    let registry = project("registry")
+        .file("src/bar-0.5.0/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("src/bar-0.5.0/src/bar.rs", r#"
+            pub fn gimme() -> String {
+                "from the registry".to_str()
+            }
+        "#);
+
+    registry.build();
+
+    registry.process("mkdir").args(["-p", "packages/bar"]).exec_with_output().assert();
+    registry.process("tar").args(["-czf", "packages/bar/bar-0.5.0.tar.gz", "-C", "src", "bar-0.5.0"])
+        .exec_with_output().assert();
+
+    let cksum = cksum.map(|c| c.to_str()).unwrap_or_else(|| tarball_cksum(&registry));
+
+    File::create(&registry.root().join("index")).write_str(format!(
+        r#"\{"name":"bar","vers":"0.5.0","deps":[],"cksum":"{}"\}"#, cksum).as_slice()).assert();
+
+    registry
+}
+
+fn tarball_cksum(registry: &ProjectBuilder) -> String {
+    sha256(&registry.root().join("packages/bar/bar-0.5.0.tar.gz")).assert()
+}
+
+// Serves the files of `registry` over HTTP, for `requests` requests.
+fn registry_server(registry: &ProjectBuilder, requests: uint) -> String {
+    let listener = TcpListener::bind("127.0.0.1", 0).assert();
+    let port = listener.socket_name().assert().port;
+    let mut acceptor = listener.listen().assert();
+    let root = registry.root();
+
+    spawn(proc() {
+        for _ in range(0, requests) {
+            let mut stream = acceptor.accept().assert();
+            let mut reader = BufferedReader::new(stream.clone());
+            let request = reader.read_line().assert();
+
+            loop {
+                if reader.read_line().assert().as_slice().trim().is_empty() {
+                    break;
+                }
+            }
+
+            // e.g. `GET /packages/bar/bar-0.5.0.tar.gz HTTP/1.1`
+            let path = request.as_slice().words().nth(1).assert();
+            let body = File::open(&root.join(path.slice_from(1))).read_to_end().assert();
+
+            stream.write_str(format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", body.len()).as_slice()).assert();
+            stream.write(body.as_slice()).assert();
+        }
+    });
+
+    format!("http://127.0.0.1:{}", port)
+}
+
+fn foo_project(registry: &ProjectBuilder) -> ProjectBuilder {
+    foo_project_at(format!("file://{}", registry.root().display()).as_slice())
+}
+
+fn foo_project_at(registry: &str) -> ProjectBuilder {
+    project("foo")
+        .file(".cargo/config", format!(r#"
+            registry = "{}"
+        "#, registry).as_slice())
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            bar = "0.5.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+}
+
+test!(cargo_compile_with_registry_dep {
+    let registry = registry(None);
+    let p = foo_project(&registry);
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_stdout(format!("Updating registry `file:{}`\nDownloading bar v0.5.0 (file:{})\nCompiling bar v0.5.0 (file:{})\nCompiling foo v0.5.0 (file:{})\n",
+                             registry.root().display(), registry.root().display(), registry.root().display(), p.root().display()))
+        .with_status(0));
+
+    assert_that(&p.root().join("target/foo"), existing_file());
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("from the registry\n"));
+})
+
+test!(cargo_compile_with_registry_checksum_mismatch {
+    let registry = registry(Some("0000"));
+    let p = foo_project(&registry);
+
+    assert_that(p.cargo_process("cargo-compile"),
+        execs()
+        .with_status(101)
+        .with_stderr(format!("unable to download packages: Checksum mismatch for bar v0.5.0 (file:{}); expected 0000, but the download was {}",
+                             registry.root().display(), tarball_cksum(&registry))));
+})
+
+test!(cargo_compile_with_registry_dep_after_checksum_mismatch {
+    let registry = registry(Some("0000"));
+    let p = foo_project(&registry);
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(101));
+
+    // Nothing that failed verification is left in the cache
+    for dir in fs::walk_dir(&paths::home().join(".cargo/registry")).assert() {
+        let name = dir.filename_str().unwrap_or("");
+        assert!(!name.ends_with(".tar.gz") && !name.ends_with(".part"), "found {}", dir.display());
+    }
+
+    File::create(&registry.root().join("index")).write_str(format!(
+        r#"\{"name":"bar","vers":"0.5.0","deps":[],"cksum":"{}"\}"#, tarball_cksum(&registry)).as_slice()).assert();
+
+    assert_that(p.process("cargo-compile").extra_path(cargo_dir()), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("from the registry\n"));
+})
+
+test!(cargo_compile_with_registry_dep_over_http {
+    let registry = registry(None);
+
+    // The index and the tarball
+    let url = registry_server(&registry, 2);
+    let p = foo_project_at(url.as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(p.root().join("target")),
+      execs().with_stdout("from the registry\n"));
+})
@@ -0,0 +1,61 @@
+use std::io::File;
+use support::{ResultTest,project,execs,cargo_dir};
+use hamcrest::{assert_that,existing_file};
+
+fn setup() {
+}
+
+test!(cargo_new_lib {
+    let p = project("new");
+
+    assert_that(p.cargo_process("cargo-new").args(["foo"]), execs().with_status(0));
+
+    let root = p.root().join("foo");
+    assert_that(&root.join("Cargo.toml"), existing_file());
+    assert_that(&root.join("src/foo.rs"), existing_file());
+    assert_eq!(File::open(&root.join(".gitignore")).read_to_str().assert().as_slice(), "/target\n");
+
+    assert_that(p.process("cargo-compile").cwd(root.clone()).extra_path(cargo_dir()),
+                execs().with_status(0));
+})
+
+test!(cargo_new_bin {
+    let p = project("new");
+
+    assert_that(p.cargo_process("cargo-new").args(["foo", "--bin"]), execs().with_status(0));
+
+    let root = p.root().join("foo");
+    assert_that(&root.join("src/main.rs"), existing_file());
+
+    assert_that(p.process("cargo-compile").cwd(root.clone()).extra_path(cargo_dir()),
+                execs().with_status(0));
+    assert_that(p.process(root.join("target/foo").display().to_str().as_slice()),
+                execs().with_stdout("Hello, world!\n"));
+})
+
+test!(cargo_new_with_git {
+    let p = project("new");
+
+    assert_that(p.cargo_process("cargo-new").args(["foo", "--git"]), execs().with_status(0));
+    assert!(p.root().join("foo/.git").is_dir());
+})
+
+test!(cargo_new_existing_directory {
+    let p = project("new")
+        .file("foo/README", "");
+
+    assert_that(p.cargo_process("cargo-new").args(["foo"]),
+                execs()
+                  .with_status(101)
+                  .with_stderr(format!("Destination `{}` already exists", p.root().join("foo").display()).as_slice()));
+})
+
+test!(cargo_new_invalid_name {
+    let p = project("new");
+
+    assert_that(p.cargo_process("cargo-new").args(["foo-bar"]),
+                execs()
+                  .with_status(101)
+                  .with_stderr("`foo-bar` is not a valid crate name; use letters, digits and underscores, starting with a letter or an underscore"));
+    assert!(!p.root().join("foo-bar").exists());
+})
@@ -0,0 +1,119 @@
+use support::{ResultTest,project,execs,main_file,cargo_dir};
+use support::paths::PathExt;
+use hamcrest::{assert_that,existing_file};
+
+fn setup() {
+}
+
+test!(cargo_package_simple {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("README.md", "# foo")
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice())
+        .file(".git/HEAD", "ref: refs/heads/master")
+        .file("target/stale", "");
+
+    assert_that(p.cargo_process("cargo-package"), execs().with_status(0));
+
+    let archive = p.root().join("target/package/foo-0.5.0.crate");
+    assert_that(&archive, existing_file());
+
+    let unpacked = p.root().join("unpacked");
+    unpacked.mkdir_p().assert();
+
+    p.process("tar").args(["-xzf".to_str(), archive.display().to_str(), "-C".to_str(), unpacked.display().to_str()])
+        .exec_with_output().assert();
+
+    assert_that(&unpacked.join("foo-0.5.0/Cargo.toml"), existing_file());
+    assert_that(&unpacked.join("foo-0.5.0/README.md"), existing_file());
+    assert_that(&unpacked.join("foo-0.5.0/src/foo.rs"), existing_file());
+    assert!(!unpacked.join("foo-0.5.0/.git").exists());
+    assert!(!unpacked.join("foo-0.5.0/target").exists());
+})
+
+test!(cargo_package_without_authors {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-package"),
+        execs()
+        .with_status(101)
+        .with_stderr("Package `foo` has no authors; at least one author is required to package it"));
+})
+
+test!(cargo_package_with_invalid_version {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "one"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-package"),
+        execs()
+        .with_status(101)
+        .with_stderr("`one` is not a valid version for package `foo`"));
+})
+
+test!(cargo_package_only_includes_package_files {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice())
+        .file("src/data/old.crate", "")
+        .file("notes.txt", "");
+    p.build();
+
+    // A link back up the tree
+    p.process("ln").args(["-s", "..", "src/up"]).exec_with_output().assert();
+
+    assert_that(p.process("cargo-package").extra_path(cargo_dir()), execs().with_status(0));
+
+    let archive = p.root().join("target/package/foo-0.5.0.crate");
+    let unpacked = p.root().join("unpacked");
+    unpacked.mkdir_p().assert();
+
+    p.process("tar").args(["-xzf".to_str(), archive.display().to_str(), "-C".to_str(), unpacked.display().to_str()])
+        .exec_with_output().assert();
+
+    assert_that(&unpacked.join("foo-0.5.0/src/foo.rs"), existing_file());
+    assert_that(&unpacked.join("foo-0.5.0/src/data/old.crate"), existing_file());
+    assert!(!unpacked.join("foo-0.5.0/notes.txt").exists());
+    assert!(!unpacked.join("foo-0.5.0/src/up").exists());
+})
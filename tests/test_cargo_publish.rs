@@ -0,0 +1,137 @@
+use std::io::{Listener,Acceptor,BufferedReader};
+use std::io::net::tcp::TcpListener;
+use std::str;
+use support::{ProjectBuilder,ResultTest,project,execs,main_file};
+use hamcrest::assert_that;
+
+fn setup() {
+}
+
+// A stand-in for a registry: accepts a single upload, answers it with
+// `status` and sends the raw request back over the returned channel.
+fn registry_server(status: &'static str) -> (String, Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1", 0).assert();
+    let port = listener.socket_name().assert().port;
+    let mut acceptor = listener.listen().assert();
+    let (tx, rx) = channel();
+
+    spawn(proc() {
+        let mut stream = acceptor.accept().assert();
+        let mut reader = BufferedReader::new(stream.clone());
+        let mut request = String::new();
+        let mut length = 0u;
+
+        loop {
+            let line = reader.read_line().assert();
+
+            if line.as_slice().trim().is_empty() {
+                break;
+            }
+
+            if line.as_slice().starts_with("Content-Length:") {
+                length = from_str(line.as_slice().slice_from(15).trim()).assert();
+            }
+
+            request.push_str(line.as_slice());
+        }
+
+        let body = reader.read_exact(length).assert();
+        request.push_str(str::from_utf8_lossy(body.as_slice()).as_slice());
+
+        stream.write_str(format!("HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n", status).as_slice()).assert();
+        tx.send(request);
+    });
+
+    (format!("http://127.0.0.1:{}", port), rx)
+}
+
+fn foo_project(registry: &str, dependencies: &str) -> ProjectBuilder {
+    project("foo")
+        .file(".cargo/config", format!(r#"
+            registry = "{}"
+            token = "secret-token"
+        "#, registry).as_slice())
+        .file("Cargo.toml", format!(r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+            {}
+
+            [[bin]]
+
+            name = "foo"
+        "#, dependencies).as_slice())
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice())
+}
+
+test!(cargo_publish_uploads_metadata_and_archive {
+    let (registry, requests) = registry_server("200 OK");
+    let p = foo_project(registry.as_slice(), r#"bar = "0.5.0""#);
+
+    assert_that(p.cargo_process("cargo-publish").args(["--no-verify"]), execs().with_status(0));
+
+    let request = requests.recv();
+
+    assert!(request.as_slice().starts_with("PUT /api/v1/packages/new "), "request was {}", request);
+    assert!(request.as_slice().contains("Authorization: secret-token"), "request was {}", request);
+    assert!(request.as_slice().contains(r#""name":"foo","version":"0.5.0""#), "request was {}", request);
+    assert!(request.as_slice().contains(r#""name":"bar""#), "request was {}", request);
+    assert!(request.as_slice().contains(r#""authors":["wycats@example.com"]"#), "request was {}", request);
+    assert!(request.as_slice().contains(r#"filename="foo-0.5.0.crate""#), "request was {}", request);
+    assert!(!request.as_slice().contains("target_dir"), "request was {}", request);
+})
+
+test!(cargo_publish_to_a_registry_answering_created {
+    let (registry, _requests) = registry_server("201 Created");
+    let p = foo_project(registry.as_slice(), "");
+
+    assert_that(p.cargo_process("cargo-publish").args(["--no-verify"]), execs().with_status(0));
+})
+
+test!(cargo_publish_with_rejected_token {
+    let (registry, _requests) = registry_server("403 Forbidden");
+    let p = foo_project(registry.as_slice(), "");
+
+    assert_that(p.cargo_process("cargo-publish").args(["--no-verify"]),
+        execs()
+        .with_status(101)
+        .with_stderr(format!("The registry at `{}` refused the token; check the `token` in .cargo/config", registry)));
+})
+
+test!(cargo_publish_duplicate_version {
+    let (registry, _requests) = registry_server("409 Conflict");
+    let p = foo_project(registry.as_slice(), "");
+
+    assert_that(p.cargo_process("cargo-publish").args(["--no-verify"]),
+        execs()
+        .with_status(101)
+        .with_stderr(format!("foo v0.5.0 has already been published to `{}`", registry)));
+})
+
+test!(cargo_publish_without_token {
+    let p = project("foo")
+        .file(".cargo/config", r#"
+            registry = "http://127.0.0.1:1"
+        "#)
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-publish"),
+        execs()
+        .with_status(101)
+        .with_stderr("No token is configured for the registry; add `token = \"<token>\"` to .cargo/config"));
+})
@@ -0,0 +1,138 @@
+use support::{project,execs};
+use hamcrest::assert_that;
+
+fn setup() {
+}
+
+static ECHO: &'static str = r#"
+    use std::os;
+
+    fn main() {
+        println!("{}", os::args().tail());
+        os::set_exit_status(os::args().len() as int - 1);
+    }
+"#;
+
+test!(cargo_run_simple {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", ECHO);
+
+    assert_that(p.cargo_process("cargo-run"),
+                execs()
+                  .with_status(0)
+                  .with_stdout(format!("Compiling foo v0.5.0 (file:{})\nRunning `target/foo`\n[]\n",
+                                       p.root().display())));
+})
+
+test!(cargo_run_with_args_and_exit_status {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", ECHO);
+
+    assert_that(p.cargo_process("cargo-run").args(["--", "a", "b"]),
+                execs()
+                  .with_status(2)
+                  .with_stdout(format!("Compiling foo v0.5.0 (file:{})\nRunning `target/foo`\n[a, b]\n",
+                                       p.root().display())));
+})
+
+test!(cargo_run_passes_help_to_the_program {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", ECHO);
+
+    assert_that(p.cargo_process("cargo-run").args(["--", "--help"]),
+                execs()
+                  .with_status(1)
+                  .with_stdout(format!("Compiling foo v0.5.0 (file:{})\nRunning `target/foo`\n[--help]\n",
+                                       p.root().display())));
+})
+
+test!(cargo_run_named_bin {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "a"
+
+            [[bin]]
+
+            name = "b"
+        "#)
+        .file("src/a.rs", r#"fn main() { println!("a") }"#)
+        .file("src/b.rs", r#"fn main() { println!("b") }"#);
+
+    assert_that(p.cargo_process("cargo-run"),
+                execs()
+                  .with_status(101)
+                  .with_stderr("Package `foo` has several bin targets (a, b); choose one with `--bin`"));
+
+    assert_that(p.cargo_process("cargo-run").args(["--bin", "b"]),
+                execs()
+                  .with_status(0)
+                  .with_stdout(format!("Compiling foo v0.5.0 (file:{})\nRunning `target/b`\nb\n",
+                                       p.root().display())));
+
+    assert_that(p.cargo_process("cargo-run").args(["--bin", "c"]),
+                execs()
+                  .with_status(101)
+                  .with_stderr("Package `foo` has no bin target named `c`; its bin targets are: a, b"));
+})
+
+test!(cargo_run_without_bin {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", "pub fn foo() {}");
+
+    assert_that(p.cargo_process("cargo-run"),
+                execs()
+                  .with_status(101)
+                  .with_stderr("Package `foo` has no bin target to run"));
+})
@@ -0,0 +1,119 @@
+use std::io::File;
+use std::str;
+use support::{ResultTest,ProjectBuilder,project,execs,main_file,cargo_dir};
+use hamcrest::{assert_that,existing_file};
+
+fn setup() {
+}
+
+fn package_input(name: &str, version: &str, root: &Path) -> String {
+    format!(r#"\{ "name": "{}", "version": "{}", "manifest_path": "{}" \}"#,
+            name, version, root.join("Cargo.toml").display())
+}
+
+fn input(root: &Path, version: &str, dependencies: &[String]) -> String {
+    format!(r#"\{ "package": {}, "dependencies": [{}] \}"#,
+            package_input("foo", version, root), dependencies.connect(", "))
+}
+
+fn foo_project() -> ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""i am foo""#, []).as_slice())
+}
+
+test!(cargo_rustc_compiles_package_from_stdin {
+    let p = foo_project();
+    p.build();
+
+    File::create(&p.root().join("input.json")).write_str(input(&p.root(), "0.5.0", []).as_slice()).assert();
+
+    let output = p.process("sh")
+        .args(["-c", "cargo-rustc < input.json"])
+        .extra_path(cargo_dir())
+        .exec_with_output()
+        .assert();
+    let stdout = str::from_utf8_lossy(output.output.as_slice()).to_str();
+
+    assert_that(&p.root().join("target/foo"), existing_file());
+    assert!(stdout.as_slice().starts_with("{\"artifacts\":[{"), "stdout was: {}", stdout);
+    assert!(stdout.as_slice().contains("\"target\":\"foo\",\"kind\":\"bin\",\"crate_types\":[\"bin\"]"), "stdout was: {}", stdout);
+    assert!(stdout.as_slice().contains(format!("\"out_dir\":\"{}\"", p.root().join("target").display()).as_slice()), "stdout was: {}", stdout);
+})
+
+test!(cargo_rustc_with_stale_input {
+    let p = foo_project();
+    p.build();
+
+    File::create(&p.root().join("input.json")).write_str(input(&p.root(), "0.6.0", []).as_slice()).assert();
+
+    assert_that(p.process("sh").args(["-c", "cargo-rustc < input.json"]).extra_path(cargo_dir()),
+                execs()
+                  .with_status(1)
+                  .with_stderr(format!("The manifest at `{}` is for foo 0.5.0, not foo 0.6.0",
+                                       p.root().join("Cargo.toml").display()).as_slice()));
+})
+
+test!(cargo_rustc_compiles_against_dependencies_from_stdin {
+    let p = project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.bar]
+
+            version = "0.5.0"
+            path = "bar"
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", main_file(r#""{}", bar::gimme()"#, ["bar"]).as_slice())
+        .file("bar/Cargo.toml", r#"
+            [project]
+
+            name = "bar"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "bar"
+        "#)
+        .file("bar/src/bar.rs", r#"
+            pub fn gimme() -> String {
+                "from bar".to_str()
+            }
+        "#);
+    p.build();
+
+    let deps = vec!(package_input("bar", "0.5.0", &p.root().join("bar")));
+    File::create(&p.root().join("input.json")).write_str(input(&p.root(), "0.5.0", deps.as_slice()).as_slice()).assert();
+
+    let output = p.process("sh")
+        .args(["-c", "cargo-rustc < input.json"])
+        .extra_path(cargo_dir())
+        .exec_with_output()
+        .assert();
+    let stdout = str::from_utf8_lossy(output.output.as_slice()).to_str();
+
+    assert!(stdout.as_slice().contains("\"target\":\"bar\",\"kind\":\"lib\""), "stdout was: {}", stdout);
+    assert!(stdout.as_slice().contains(format!("\"out_dir\":\"{}\"", p.root().join("target/deps").display()).as_slice()), "stdout was: {}", stdout);
+
+    assert_that(p.process(p.root().join("target/foo").display().to_str().as_slice()),
+                execs().with_stdout("from bar\n"));
+})
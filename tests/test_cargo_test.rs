@@ -0,0 +1,110 @@
+use std::io::process::{ProcessExit,ExitStatus};
+use std::str;
+use support::{ProjectBuilder,project,execs};
+use hamcrest::{assert_that,existing_file};
+use cargo::util::CargoError;
+use cargo::util::result::ProcessError;
+
+fn setup() {
+}
+
+fn lib_project(tests: &str) -> ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "foo"
+        "#)
+        .file("src/foo.rs", format!(r#"
+            pub fn gimme() -> String \{
+                "test passed".to_str()
+            \}
+
+            {}
+        "#, tests).as_slice())
+        .file("tests/integration.rs", r#"
+            extern crate foo;
+
+            #[test]
+            fn integration_gimme() {
+                assert_eq!(foo::gimme().as_slice(), "test passed");
+            }
+        "#)
+}
+
+// Runs cargo-test, returning its exit status and stdout
+fn run(p: &ProjectBuilder, args: &[&str]) -> (ProcessExit, String) {
+    let output = match p.cargo_process("cargo-test").args(args).exec_with_output() {
+        Ok(output) => output,
+        Err(CargoError { kind: ProcessError(_, Some(output)), .. }) => output,
+        Err(err) => fail!("could not run cargo-test: {}", err)
+    };
+
+    (output.status, str::from_utf8_lossy(output.output.as_slice()).to_str())
+}
+
+test!(cargo_test_runs_unit_and_integration_tests {
+    let p = lib_project(r#"
+        #[test]
+        fn unit_gimme() {
+            assert_eq!(gimme().as_slice(), "test passed");
+        }
+    "#);
+
+    let (status, out) = run(&p, []);
+
+    assert_eq!(status, ExitStatus(0));
+
+    assert_that(&p.root().join("target/test/foo-lib"), existing_file());
+    assert_that(&p.root().join("target/test/integration"), existing_file());
+
+    assert!(out.as_slice().contains("test unit_gimme ... ok"), "stdout was {}", out);
+    assert!(out.as_slice().contains("test integration_gimme ... ok"), "stdout was {}", out);
+    assert!(out.as_slice().contains("Test result: 2 passed; 0 failed; 0 ignored"), "stdout was {}", out);
+})
+
+test!(cargo_test_with_filter {
+    let p = lib_project(r#"
+        #[test]
+        fn unit_gimme() {
+            assert_eq!(gimme().as_slice(), "test passed");
+        }
+    "#);
+
+    let (status, out) = run(&p, ["integration"]);
+
+    assert_eq!(status, ExitStatus(0));
+
+    assert!(!out.as_slice().contains("unit_gimme"), "stdout was {}", out);
+    assert!(out.as_slice().contains("Test result: 1 passed; 0 failed; 0 ignored"), "stdout was {}", out);
+})
+
+test!(cargo_test_with_several_filters {
+    let p = lib_project("");
+
+    assert_that(p.cargo_process("cargo-test").args(["unit", "integration"]),
+                execs()
+                  .with_status(1)
+                  .with_stderr("Only one test name filter can be given, but got unit integration"));
+})
+
+test!(cargo_test_with_failing_test {
+    let p = lib_project(r#"
+        #[test]
+        fn unit_fails() {
+            assert_eq!(gimme().as_slice(), "test failed");
+        }
+    "#);
+
+    let (status, out) = run(&p, []);
+
+    assert_eq!(status, ExitStatus(101));
+    assert!(out.as_slice().contains("test unit_fails ... FAILED"), "stdout was {}", out);
+    assert!(out.as_slice().contains("Test result: 1 passed; 1 failed; 0 ignored"), "stdout was {}", out);
+})
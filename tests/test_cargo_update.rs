@@ -0,0 +1,95 @@
+use std::io::File;
+use support::{ProjectBuilder,ResultTest,project,execs,main_file,cargo_dir,git_repo};
+use hamcrest::assert_that;
+use cargo;
+
+fn setup() {
+}
+
+fn dep1_repo() -> ProjectBuilder {
+    git_repo("dep1", |project| {
+        project
+            .file("Cargo.toml", r#"
+                [project]
+
+                name = "dep1"
+                version = "0.5.0"
+                authors = ["carlhuda@example.com"]
+
+                [[lib]]
+
+                name = "dep1"
+            "#)
+            .file("src/dep1.rs", r#"
+                pub fn hello() -> &'static str {
+                    "hello world"
+                }
+            "#)
+    }).assert()
+}
+
+fn foo_project(git_project: &ProjectBuilder) -> ProjectBuilder {
+    project("foo")
+        .file("Cargo.toml", format!(r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies.dep1]
+
+            version = "0.5.0"
+            git = "file://{}"
+
+            [[bin]]
+
+            name = "foo"
+        "#, git_project.root().display()))
+        .file("src/foo.rs", main_file(r#""{}", dep1::hello()"#, ["dep1"]))
+}
+
+fn head(git_project: &ProjectBuilder) -> String {
+    let output = git_project.process("git").args(["rev-parse", "HEAD"]).exec_with_output().assert();
+    String::from_utf8(output.output).assert().as_slice().trim().to_str()
+}
+
+test!(cargo_update_moves_git_dep_to_new_revision {
+    let git_project = dep1_repo();
+    let project = foo_project(&git_project);
+    let old_rev = head(&git_project);
+
+    assert_that(project.cargo_process("cargo-compile"), execs().with_status(0));
+
+    File::create(&git_project.root().join("src/dep1.rs")).write_str(r#"
+        pub fn hello() -> &'static str {
+            "goodbye world"
+        }
+    "#).assert();
+    git_project.process("git").args(["commit", "-a", "-m", "Second commit"]).exec_with_output().assert();
+    let new_rev = head(&git_project);
+
+    assert_that(project.process("cargo-update").extra_path(cargo_dir()),
+        execs()
+        .with_stdout(format!("Updating git repository `file:{}`\nUpdating dep1 v0.5.0 (rev {}) -> v0.5.0 (rev {})\n",
+                             git_project.root().display(), old_rev, new_rev))
+        .with_status(0));
+
+    assert_that(project.process("cargo-compile").extra_path(cargo_dir()), execs().with_status(0));
+
+    assert_that(
+      cargo::util::process("foo").extra_path(project.root().join("target")),
+      execs().with_stdout("goodbye world\n"));
+})
+
+test!(cargo_update_unknown_package {
+    let git_project = dep1_repo();
+    let project = foo_project(&git_project);
+
+    assert_that(project.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(project.process("cargo-update").extra_path(cargo_dir()).args(["--package", "bar"]),
+        execs()
+        .with_status(101)
+        .with_stderr("Package `bar` is not a dependency in Cargo.lock"));
+})
@@ -0,0 +1,260 @@
+use std::str;
+use support::{ResultTest,ProjectBuilder,project,execs,main_file,cargo_dir};
+use hamcrest::{assert_that,existing_file};
+
+fn setup() {
+}
+
+fn workspace() -> ProjectBuilder {
+    let p = project("ws");
+    let common = p.root().join("common");
+
+    p.file(".cargo/config", format!(r#"
+            paths = ["{}"]
+        "#, common.display()).as_slice())
+        .file("Cargo.toml", r#"
+            [workspace]
+
+            members = ["a", "b"]
+        "#)
+        .file("a/Cargo.toml", r#"
+            [project]
+
+            name = "a"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            common = "0.5.0"
+
+            [[bin]]
+
+            name = "a"
+        "#)
+        .file("a/src/a.rs", main_file(r#""{}", common::gimme()"#, ["common"]).as_slice())
+        .file("b/Cargo.toml", r#"
+            [project]
+
+            name = "b"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            common = "0.5.0"
+
+            [[bin]]
+
+            name = "b"
+        "#)
+        .file("b/src/b.rs", main_file(r#""{}", common::gimme()"#, ["common"]).as_slice())
+        .file("common/Cargo.toml", r#"
+            [project]
+
+            name = "common"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "common"
+        "#)
+        .file("common/src/common.rs", r#"
+            pub fn gimme() -> String {
+                "shared".to_str()
+            }
+        "#)
+}
+
+/**
+ * A workspace whose member `b` depends on its member `a`.
+ */
+fn dependent_members() -> ProjectBuilder {
+    project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+
+            members = ["a", "b"]
+        "#)
+        .file("a/Cargo.toml", r#"
+            [project]
+
+            name = "a"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[lib]]
+
+            name = "a"
+        "#)
+        .file("a/src/a.rs", r#"
+            pub fn gimme() -> String {
+                "from a".to_str()
+            }
+        "#)
+        .file("b/Cargo.toml", r#"
+            [project]
+
+            name = "b"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [dependencies]
+
+            a = "0.5.0"
+
+            [[bin]]
+
+            name = "b"
+        "#)
+        .file("b/src/b.rs", main_file(r#""{}", a::gimme()"#, ["a"]).as_slice())
+}
+
+fn compile_all(p: &ProjectBuilder) -> String {
+    let output = p.process("cargo-compile").args(["--all"]).extra_path(cargo_dir()).exec_with_output().assert();
+    str::from_utf8_lossy(output.output.as_slice()).to_str()
+}
+
+test!(cargo_compile_workspace_all {
+    let p = workspace();
+
+    assert_that(p.cargo_process("cargo-compile").args(["--all"]), execs().with_status(0));
+
+    assert_that(&p.root().join("target/a"), existing_file());
+    assert_that(&p.root().join("target/b"), existing_file());
+    assert_that(&p.root().join("Cargo.lock"), existing_file());
+    assert!(!p.root().join("a/target").exists());
+    assert!(!p.root().join("b/target").exists());
+    assert!(!p.root().join("a/Cargo.lock").exists());
+
+    assert_that(p.process(p.root().join("target/b").display().to_str().as_slice()),
+                execs().with_stdout("shared\n"));
+})
+
+test!(cargo_compile_workspace_from_virtual_root {
+    let p = workspace();
+
+    assert_that(p.cargo_process("cargo-compile"), execs().with_status(0));
+
+    assert_that(&p.root().join("target/a"), existing_file());
+    assert_that(&p.root().join("target/b"), existing_file());
+})
+
+test!(cargo_compile_workspace_member {
+    let p = workspace();
+    p.build();
+
+    assert_that(p.process("cargo-compile").cwd(p.root().join("b")).extra_path(cargo_dir()),
+                execs().with_status(0));
+
+    assert_that(&p.root().join("target/b"), existing_file());
+    assert!(!p.root().join("target/a").exists());
+    assert_that(&p.root().join("Cargo.lock"), existing_file());
+    assert!(!p.root().join("b/target").exists());
+    assert!(!p.root().join("b/Cargo.lock").exists());
+})
+
+test!(cargo_locate_project_in_workspace {
+    let p = workspace();
+    p.build();
+
+    assert_that(p.process("cargo").args(["locate-project"]).cwd(p.root().join("b")).extra_path(cargo_dir()),
+                execs()
+                  .with_status(0)
+                  .with_stdout(format!(r#"\{"root":"{}","workspace_root":"{}"\}"#,
+                                       p.root().join("b").display(), p.root().display()).append("\n")));
+})
+
+test!(cargo_update_workspace_member {
+    let p = workspace();
+    p.build();
+
+    assert_that(p.process("cargo-update").cwd(p.root().join("b")).extra_path(cargo_dir()),
+                execs().with_status(0));
+
+    assert_that(&p.root().join("Cargo.lock"), existing_file());
+    assert!(!p.root().join("b/Cargo.lock").exists());
+})
+
+test!(cargo_test_workspace_member {
+    let p = workspace();
+    p.build();
+
+    assert_that(p.process("cargo-test").cwd(p.root().join("a")).extra_path(cargo_dir()),
+                execs().with_status(0));
+
+    assert!(p.root().join("target/test").is_dir());
+    assert!(!p.root().join("a/target").exists());
+    assert!(!p.root().join("a/Cargo.lock").exists());
+})
+
+test!(cargo_compile_workspace_with_invalid_root_package {
+    let p = project("ws")
+        .file("Cargo.toml", r#"
+            [workspace]
+
+            members = []
+
+            [project]
+
+            name = "ws"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "ws"
+        "#)
+        .file("src/ws.rs", main_file(r#""i am ws""#, []).as_slice());
+
+    assert_that(p.cargo_process("cargo-compile"),
+                execs()
+                  .with_status(101)
+                  .with_stderr(format!("Could not read the workspace root package at `{}`",
+                                       p.root().join("Cargo.toml").display())));
+})
+
+test!(cargo_compile_workspace_compiles_shared_dependency_once {
+    let p = workspace();
+    p.build();
+
+    let out = compile_all(&p);
+    let compiled: Vec<&str> = out.as_slice().lines().filter(|line| line.starts_with("Compiling common ")).collect();
+
+    assert!(compiled.len() == 1, "stdout was {}", out);
+})
+
+test!(cargo_compile_workspace_with_dependent_members_is_fresh {
+    let p = dependent_members();
+    p.build();
+
+    compile_all(&p);
+    assert_that(p.process(p.root().join("target/b").display().to_str().as_slice()),
+                execs().with_stdout("from a\n"));
+
+    let out = compile_all(&p);
+    assert!(out.as_slice().lines().all(|line| line.starts_with("Fresh ")), "stdout was {}", out);
+})
+
+test!(cargo_compile_below_an_invalid_manifest {
+    let p = project("outer")
+        .file("Cargo.toml", "[project")
+        .file("foo/Cargo.toml", r#"
+            [project]
+
+            name = "foo"
+            version = "0.5.0"
+            authors = ["wycats@example.com"]
+
+            [[bin]]
+
+            name = "foo"
+        "#)
+        .file("foo/src/foo.rs", main_file(r#""i am foo""#, []).as_slice());
+    p.build();
+
+    assert_that(p.process("cargo-compile").cwd(p.root().join("foo")).extra_path(cargo_dir()),
+                execs().with_status(0));
+
+    assert_that(&p.root().join("foo/target/foo"), existing_file());
+})
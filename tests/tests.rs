@@ -23,3 +23,14 @@ macro_rules! test(
 mod test_cargo_compile;
 mod test_cargo_compile_git_deps;
 mod test_shell;
+mod test_cargo_update;
+mod test_cargo_compile_registry;
+mod test_cargo_package;
+mod test_cargo_publish;
+mod test_cargo_test;
+mod test_cargo;
+mod test_cargo_rustc;
+mod test_cargo_new;
+mod test_cargo_run;
+mod test_cargo_clean;
+mod test_cargo_workspace;